@@ -0,0 +1,427 @@
+use crate::{
+    property::{Properties, PropertyIndex},
+    verdict::Verdict,
+    Bdd, BddManager,
+};
+use fsmbdd::{FsmBdd, Trans, TransBddMethod};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+#[derive(Debug, Clone)]
+pub struct Latch {
+    pub lit: usize,
+    pub next: usize,
+    /// 0, 1, or the latch literal itself when uninitialized.
+    pub reset: usize,
+}
+
+/// An And-Inverter Graph in the AIGER 1.9 format.
+#[derive(Debug, Clone, Default)]
+pub struct Aiger {
+    pub max_var: usize,
+    pub inputs: Vec<usize>,
+    pub latches: Vec<Latch>,
+    pub outputs: Vec<usize>,
+    pub ands: Vec<(usize, usize, usize)>,
+    pub bad: Vec<usize>,
+    pub constraints: Vec<usize>,
+    pub justice: Vec<Vec<usize>>,
+    pub fairness: Vec<usize>,
+}
+
+fn lit_bdd(vars: &HashMap<usize, Bdd>, lit: usize) -> Bdd {
+    let bdd = vars[&(lit / 2)].clone();
+    if lit % 2 == 1 {
+        !bdd
+    } else {
+        bdd
+    }
+}
+
+pub fn is_aiger(path: &str) -> bool {
+    path.ends_with(".aag") || path.ends_with(".aig")
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn line(&mut self) -> Result<&'a str, String> {
+        if self.pos >= self.data.len() {
+            return Err("unexpected end of aiger file".to_string());
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+        let line = &self.data[start..self.pos];
+        self.pos += 1;
+        std::str::from_utf8(line).map_err(|e| e.to_string())
+    }
+
+    fn numbers(&mut self) -> Result<Vec<usize>, String> {
+        self.line()?
+            .split_whitespace()
+            .map(|x| x.parse::<usize>().map_err(|e| format!("{}: '{}'", e, x)))
+            .collect()
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let numbers = self.numbers()?;
+        if numbers.len() != 1 {
+            return Err(format!("expected one literal per line, found {:?}", numbers));
+        }
+        Ok(numbers[0])
+    }
+
+    fn delta(&mut self) -> Result<usize, String> {
+        let mut x = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| "unexpected end of aiger and gates".to_string())?;
+            self.pos += 1;
+            x |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(x);
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl Aiger {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, pos: 0 };
+        let header = reader.line()?;
+        let mut fields = header.split_whitespace();
+        let format = fields.next().unwrap_or_default();
+        let binary = match format {
+            "aag" => false,
+            "aig" => true,
+            _ => return Err(format!("unknown aiger format '{}'", format)),
+        };
+        let counts = fields
+            .map(|x| x.parse::<usize>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<usize>, String>>()?;
+        if counts.len() < 5 {
+            return Err(format!("invalid aiger header '{}'", header));
+        }
+        let count = |i: usize| counts.get(i).copied().unwrap_or(0);
+        let (m, i, l, o, a) = (count(0), count(1), count(2), count(3), count(4));
+        let (b, c, j, f) = (count(5), count(6), count(7), count(8));
+        let mut aiger = Aiger {
+            max_var: m,
+            ..Default::default()
+        };
+        for k in 0..i {
+            aiger
+                .inputs
+                .push(if binary { 2 * (k + 1) } else { reader.number()? });
+        }
+        for k in 0..l {
+            let mut line = reader.numbers()?;
+            if binary {
+                line.insert(0, 2 * (i + k + 1));
+            }
+            if line.len() < 2 {
+                return Err(format!("invalid aiger latch {:?}", line));
+            }
+            let reset = line.get(2).copied().unwrap_or(0);
+            aiger.latches.push(Latch {
+                lit: line[0],
+                next: line[1],
+                reset,
+            });
+        }
+        for _ in 0..o {
+            aiger.outputs.push(reader.number()?);
+        }
+        for _ in 0..b {
+            aiger.bad.push(reader.number()?);
+        }
+        for _ in 0..c {
+            aiger.constraints.push(reader.number()?);
+        }
+        let mut justice_size = Vec::new();
+        for _ in 0..j {
+            justice_size.push(reader.number()?);
+        }
+        for size in justice_size {
+            let mut justice = Vec::new();
+            for _ in 0..size {
+                justice.push(reader.number()?);
+            }
+            aiger.justice.push(justice);
+        }
+        for _ in 0..f {
+            aiger.fairness.push(reader.number()?);
+        }
+        for k in 0..a {
+            if binary {
+                let lhs = 2 * (i + l + k + 1);
+                let rhs0 = lhs - reader.delta()?;
+                let rhs1 = rhs0 - reader.delta()?;
+                aiger.ands.push((lhs, rhs0, rhs1));
+            } else {
+                let line = reader.numbers()?;
+                if line.len() != 3 {
+                    return Err(format!("invalid aiger and gate {:?}", line));
+                }
+                aiger.ands.push((line[0], line[1], line[2]));
+            }
+        }
+        aiger.validate()?;
+        Ok(aiger)
+    }
+
+    /// Checks that every defined literal is a positive variable up to the maximal variable
+    /// index, defined once, and that every used literal refers to a defined variable.
+    fn validate(&self) -> Result<(), String> {
+        let mut defined = HashSet::from([0]);
+        let lhs = self
+            .inputs
+            .iter()
+            .chain(self.latches.iter().map(|latch| &latch.lit));
+        for lit in lhs.chain(self.ands.iter().map(|(lhs, _, _)| lhs)) {
+            if lit % 2 == 1 || lit / 2 > self.max_var || !defined.insert(lit / 2) {
+                return Err(format!("invalid aiger definition of literal {}", lit));
+            }
+        }
+        let used = self
+            .latches
+            .iter()
+            .map(|latch| &latch.next)
+            .chain(self.ands.iter().flat_map(|(_, rhs0, rhs1)| [rhs0, rhs1]))
+            .chain(self.outputs.iter())
+            .chain(self.bad.iter())
+            .chain(self.constraints.iter())
+            .chain(self.justice.iter().flatten())
+            .chain(self.fairness.iter());
+        for lit in used {
+            if !defined.contains(&(lit / 2)) {
+                return Err(format!("undefined aiger literal {}", lit));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(&data)
+    }
+
+    /// Builds the model with inputs named `i<k>` and latches named `l<k>`, together with
+    /// its bad, justice and fairness properties.
+    pub fn to_fsmbdd(
+        &self,
+        manager: &BddManager,
+        method: TransBddMethod,
+    ) -> (FsmBdd<BddManager>, Properties) {
+        let mut symbols = HashMap::new();
+        let mut vars = HashMap::new();
+        vars.insert(0, manager.constant(false));
+        for (k, input) in self.inputs.iter().enumerate() {
+            let id = symbols.len() * 2;
+            symbols.insert(format!("i{}", k), id);
+            vars.insert(input / 2, manager.ith_var(id));
+        }
+        for (k, latch) in self.latches.iter().enumerate() {
+            let id = symbols.len() * 2;
+            symbols.insert(format!("l{}", k), id);
+            vars.insert(latch.lit / 2, manager.ith_var(id));
+            manager.ith_var(id + 1);
+        }
+        let ands: HashMap<usize, (usize, usize)> = self
+            .ands
+            .iter()
+            .map(|(lhs, rhs0, rhs1)| (lhs / 2, (*rhs0, *rhs1)))
+            .collect();
+        let mut stack: Vec<usize> = self.ands.iter().map(|(lhs, _, _)| lhs / 2).collect();
+        while let Some(var) = stack.pop() {
+            if vars.contains_key(&var) {
+                continue;
+            }
+            let (rhs0, rhs1) = ands[&var];
+            let missing: Vec<usize> = [rhs0 / 2, rhs1 / 2]
+                .into_iter()
+                .filter(|x| !vars.contains_key(x))
+                .collect();
+            if missing.is_empty() {
+                let bdd = lit_bdd(&vars, rhs0) & lit_bdd(&vars, rhs1);
+                vars.insert(var, bdd);
+            } else {
+                stack.push(var);
+                stack.extend(missing);
+            }
+        }
+        let mut init = manager.constant(true);
+        let mut trans = Vec::new();
+        for latch in self.latches.iter() {
+            let current = lit_bdd(&vars, latch.lit);
+            let next = lit_bdd(&vars, latch.next);
+            let next_var = current.next_state();
+            trans.push((&next_var & &next) | (!next_var & !next));
+            match latch.reset {
+                0 => init &= !current,
+                1 => init &= current,
+                _ => (),
+            }
+        }
+        let mut invariants = manager.constant(true);
+        for constraint in self.constraints.iter() {
+            invariants &= lit_bdd(&vars, *constraint);
+        }
+        let properties = Properties {
+            bad: self
+                .bad
+                .iter()
+                .map(|lit| lit_bdd(&vars, *lit))
+                .collect(),
+            justice: self
+                .justice
+                .iter()
+                .map(|justice| {
+                    justice
+                        .iter()
+                        .map(|lit| lit_bdd(&vars, *lit))
+                        .collect()
+                })
+                .collect(),
+            fairness: self
+                .fairness
+                .iter()
+                .map(|lit| lit_bdd(&vars, *lit))
+                .collect(),
+        };
+        let fsmbdd = FsmBdd {
+            symbols,
+            manager: manager.clone(),
+            init,
+            invariants,
+            trans: Trans::new(manager, trans, method),
+            justice: Vec::new(),
+        };
+        (fsmbdd, properties)
+    }
+
+    /// HWMCC result of `property` in the AIGER witness format, with the witness body only
+    /// when the violation comes with a counterexample.
+    pub fn witness(&self, property: PropertyIndex, verdict: &Verdict) -> String {
+        let kind = property
+            .kind
+            .unwrap_or(if self.justice.is_empty() { 'b' } else { 'j' });
+        let index = property.index;
+        let (result, trace) = match verdict {
+            Verdict::Holds => (0, None),
+            Verdict::Violated(trace) => (1, trace.as_ref()),
+            Verdict::Unknown(_) => (2, None),
+        };
+        let mut witness = format!("{}\n{}{}\n", result, kind, index);
+        if let Some(trace) = trace {
+            let value = |state: &[(String, bool)], name: String| {
+                state
+                    .iter()
+                    .find(|(symbol, _)| *symbol == name)
+                    .map_or('x', |(_, value)| if *value { '1' } else { '0' })
+            };
+            if let Some(first) = trace.states.first() {
+                witness.extend((0..self.latches.len()).map(|k| value(first, format!("l{}", k))));
+                witness.push('\n');
+            }
+            for state in trace.states.iter() {
+                witness.extend((0..self.inputs.len()).map(|k| value(state, format!("i{}", k))));
+                witness.push('\n');
+            }
+        }
+        witness.push_str(".\n");
+        witness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    /// An input, a latch following the conjunction of the input and the latch, the bad
+    /// property `!i & !l` and the justice property `l`.
+    const AAG: &str = "aag 4 1 1 0 2 1 0 1\n2\n4 6\n8\n1\n4\n6 2 4\n8 3 5\n";
+
+    #[test]
+    fn parse_ascii() {
+        let aiger = Aiger::parse(AAG.as_bytes()).unwrap();
+        assert_eq!(aiger.max_var, 4);
+        assert_eq!(aiger.inputs, vec![2]);
+        assert_eq!(aiger.latches.len(), 1);
+        let latch = &aiger.latches[0];
+        assert_eq!((latch.lit, latch.next, latch.reset), (4, 6, 0));
+        assert_eq!(aiger.bad, vec![8]);
+        assert_eq!(aiger.justice, vec![vec![4]]);
+        assert_eq!(aiger.ands, vec![(6, 2, 4), (8, 3, 5)]);
+    }
+
+    #[test]
+    fn parse_binary() {
+        let mut aig = b"aig 4 1 1 0 2 1 0 1\n6\n8\n1\n4\n".to_vec();
+        aig.extend([2, 2, 3, 2]);
+        let aiger = Aiger::parse(&aig).unwrap();
+        assert_eq!(aiger.inputs, vec![2]);
+        assert_eq!(aiger.latches[0].lit, 4);
+        assert_eq!(aiger.latches[0].next, 6);
+        assert_eq!(aiger.bad, vec![8]);
+        assert_eq!(aiger.justice, vec![vec![4]]);
+        assert_eq!(aiger.ands, vec![(6, 4, 2), (8, 5, 3)]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Aiger::parse(b"aag 1 0 1 0 0 1\n2 3\n").is_err());
+        assert!(Aiger::parse(b"aag 1 0\n").is_err());
+        assert!(Aiger::parse(b"xyz 1 0 0 0 0\n").is_err());
+        // and gate over an undefined variable
+        assert!(Aiger::parse(b"aag 2 1 0 0 1\n2\n4 2 6\n").is_err());
+        // literal beyond the maximal variable index
+        assert!(Aiger::parse(b"aag 1 1 0 0 0 1\n2\n4\n").is_err());
+    }
+
+    #[test]
+    fn to_fsmbdd() {
+        let manager = crate::test_manager();
+        let aiger = Aiger::parse(AAG.as_bytes()).unwrap();
+        let (fsmbdd, properties) = aiger.to_fsmbdd(&manager, TransBddMethod::Partition);
+        let i = manager.ith_var(fsmbdd.symbols["i0"]);
+        let l = manager.ith_var(fsmbdd.symbols["l0"]);
+        assert!(fsmbdd.init == !&l);
+        assert!(properties.bad == vec![!&i & !&l]);
+        assert!(properties.justice == vec![vec![l.clone()]]);
+        // the latch stays 0 from the initial states, whatever the input
+        assert!(fsmbdd.post_image(&fsmbdd.init) == !&l);
+        assert!(fsmbdd.post_image(&(&i & &l)) == l);
+    }
+
+    #[test]
+    fn witness() {
+        let aiger = Aiger::parse(AAG.as_bytes()).unwrap();
+        let bad = PropertyIndex {
+            kind: Some('b'),
+            index: 0,
+        };
+        let state = |i, l| vec![("i0".to_string(), i), ("l0".to_string(), l)];
+        let trace = Trace {
+            states: vec![state(false, false)],
+            loop_start: None,
+            ..Default::default()
+        };
+        let witness = aiger.witness(bad, &Verdict::Violated(Some(trace)));
+        assert_eq!(witness, "1\nb0\n0\n0\n.\n");
+        assert_eq!(aiger.witness(bad, &Verdict::Holds), "0\nb0\n.\n");
+        assert_eq!(aiger.witness(bad, &Verdict::Violated(None)), "1\nb0\n.\n");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Bdd, BddManager};
+use crate::{cache::translate, Bdd, BddManager};
 use fsmbdd::{FsmBdd, Trans, TransBddMethod};
 use logic_form::Expr;
 use nom::{
@@ -10,6 +10,16 @@ use nom::{
 };
 use std::{collections::HashMap, process::Command};
 
+/// Strength of a Büchi automaton. In a weak automaton every strongly connected component
+/// is either all accepting or all rejecting, and a terminal automaton is a weak automaton
+/// whose accepting states can not be left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strength {
+    Terminal,
+    Weak,
+    Strong,
+}
+
 #[derive(Debug, Clone)]
 pub struct BuchiAutomata {
     pub manager: BddManager,
@@ -21,7 +31,7 @@ pub struct BuchiAutomata {
 }
 
 impl BuchiAutomata {
-    fn new(manager: BddManager) -> Self {
+    pub(crate) fn new(manager: BddManager) -> Self {
         Self {
             symbols: HashMap::new(),
             manager,
@@ -59,6 +69,145 @@ impl BuchiAutomata {
     pub fn add_accepting_state(&mut self, state: usize) {
         self.accepting_states.push(state);
     }
+
+    /// Strongly connected component of every state, with Tarjan's algorithm over the edges
+    /// with satisfiable labels.
+    pub fn sccs(&self) -> Vec<usize> {
+        struct Tarjan<'a> {
+            automata: &'a BuchiAutomata,
+            index: Vec<Option<usize>>,
+            lowlink: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            scc: Vec<usize>,
+            next_index: usize,
+            num_scc: usize,
+        }
+        impl Tarjan<'_> {
+            fn visit(&mut self, state: usize) {
+                self.index[state] = Some(self.next_index);
+                self.lowlink[state] = self.next_index;
+                self.next_index += 1;
+                self.stack.push(state);
+                self.on_stack[state] = true;
+                for (next, label) in self.automata.forward[state].iter() {
+                    if label.is_constant(false) {
+                        continue;
+                    }
+                    match self.index[*next] {
+                        None => {
+                            self.visit(*next);
+                            self.lowlink[state] = self.lowlink[state].min(self.lowlink[*next]);
+                        }
+                        Some(index) if self.on_stack[*next] => {
+                            self.lowlink[state] = self.lowlink[state].min(index);
+                        }
+                        _ => (),
+                    }
+                }
+                if Some(self.lowlink[state]) == self.index[state] {
+                    loop {
+                        let top = self.stack.pop().unwrap();
+                        self.on_stack[top] = false;
+                        self.scc[top] = self.num_scc;
+                        if top == state {
+                            break;
+                        }
+                    }
+                    self.num_scc += 1;
+                }
+            }
+        }
+        let num_state = self.num_state();
+        let mut tarjan = Tarjan {
+            automata: self,
+            index: vec![None; num_state],
+            lowlink: vec![0; num_state],
+            stack: Vec::new(),
+            on_stack: vec![false; num_state],
+            scc: vec![0; num_state],
+            next_index: 0,
+            num_scc: 0,
+        };
+        for state in 0..num_state {
+            if tarjan.index[state].is_none() {
+                tarjan.visit(state);
+            }
+        }
+        tarjan.scc
+    }
+
+    /// Strength of the automaton with `accepting_states` as its accepting states.
+    pub fn strength(&self, accepting_states: &[usize]) -> Strength {
+        let sccs = self.sccs();
+        let accepting: Vec<bool> = (0..self.num_state())
+            .map(|state| accepting_states.contains(&state))
+            .collect();
+        for state in 0..self.num_state() {
+            for (next, label) in self.forward[state].iter() {
+                let inner = sccs[state] == sccs[*next] && !label.is_constant(false);
+                if inner && accepting[state] != accepting[*next] {
+                    return Strength::Strong;
+                }
+            }
+        }
+        for state in accepting_states.iter() {
+            let mut stay = self.manager.constant(false);
+            for (next, label) in self.forward[*state].iter() {
+                if accepting[*next] {
+                    stay |= label;
+                }
+            }
+            if !stay.is_constant(true) {
+                return Strength::Weak;
+            }
+        }
+        Strength::Terminal
+    }
+
+    /// Accepting states that make up the safety part of the automaton: from them, the run
+    /// can always move on to another of them, so reaching one of them with a model state
+    /// that has an infinite path already is a counterexample.
+    pub fn safety_states(&self) -> Vec<usize> {
+        let mut safety = self.accepting_states.clone();
+        loop {
+            let closed: Vec<usize> = safety
+                .iter()
+                .copied()
+                .filter(|state| {
+                    let mut stay = self.manager.constant(false);
+                    for (next, label) in self.forward[*state].iter() {
+                        if safety.contains(next) {
+                            stay |= label;
+                        }
+                    }
+                    stay.is_constant(true)
+                })
+                .collect();
+            if closed.len() == safety.len() {
+                break safety;
+            }
+            safety = closed;
+        }
+    }
+}
+
+pub const LTL2BA: &str = "./ltl2ba/ltl2ba";
+
+/// The never claim produced by ltl2ba for `ltl`.
+pub fn ltl2ba(ltl: &smv::Expr) -> String {
+    let ltl2dfa = Command::new(LTL2BA)
+        .arg("-f")
+        .arg(format!("{}", ltl))
+        .output()
+        .unwrap();
+    // let ltl2dfa = Command::new("/root/spot-2.11.5/bin/ltl2tgba")
+    //     .arg("-s")
+    //     .arg("-f")
+    //     .arg(format!("{}", ltl))
+    //     .output()
+    //     .unwrap();
+    String::from_utf8_lossy(&ltl2dfa.stdout).into_owned()
 }
 
 fn skip_line(input: &str) -> IResult<&str, &str> {
@@ -89,24 +238,30 @@ fn parse_state(input: &str) -> IResult<&str, (&str, Vec<(&str, &str)>)> {
     Ok((input, (ident, trans)))
 }
 
-impl BuchiAutomata {
+/// An automaton as produced by the translator, with edge labels in ltl2ba syntax over the
+/// atomic propositions of the formula.
+#[derive(Debug, Clone, Default)]
+pub struct RawAutomata {
+    pub num_state: usize,
+    pub init_states: Vec<usize>,
+    pub accepting_states: Vec<usize>,
+    pub edges: Vec<(usize, usize, String)>,
+}
+
+impl RawAutomata {
     fn state_ident_get_id<'a>(
         &mut self,
         map: &mut HashMap<&'a str, usize>,
         ident: &'a str,
     ) -> usize {
-        map.get(ident)
-            .copied()
-            .or_else(|| {
-                assert!(map.insert(ident, self.num_state()).is_none());
-                self.extend_to(self.num_state());
-                map.get(ident).copied()
-            })
-            .unwrap()
+        *map.entry(ident).or_insert_with(|| {
+            self.num_state += 1;
+            self.num_state - 1
+        })
     }
 
-    fn parse(input: &str, manager: &BddManager, symbols: &HashMap<String, Bdd>) -> Self {
-        let mut ret = Self::new(manager.clone());
+    pub fn from_never_claim(input: &str) -> Self {
+        let mut ret = Self::default();
         let mut state_map = HashMap::new();
         let (input, _) = skip_line(input).unwrap();
         let (input, states) = many1(parse_state)(input).unwrap();
@@ -115,58 +270,97 @@ impl BuchiAutomata {
         for (ident, trans) in states {
             let state_id = ret.state_ident_get_id(&mut state_map, ident);
             if ident.starts_with("accept_") {
-                ret.add_accepting_state(state_id);
+                ret.accepting_states.push(state_id);
             }
             if ident.ends_with("_init") {
-                ret.add_init_state(state_id);
+                ret.init_states.push(state_id);
             }
-            let mut cond = manager.constant(true);
-            for i in 0..trans.len() {
-                let edge = trans[i].0;
-                let dist = trans[i].1;
+            for (edge, dist) in trans {
                 let dist = ret.state_ident_get_id(&mut state_map, dist);
-                let edge = if edge == "(1)" {
-                    Expr::LitExpr(true)
-                } else {
-                    Expr::from(edge)
-                };
-                let edge_bdd = edge.to_bdd(manager, symbols);
-                cond &= !&edge_bdd;
-                ret.add_edge(state_id, dist, edge_bdd);
+                ret.edges.push((state_id, dist, edge.to_string()));
             }
         }
         ret
     }
+}
+
+impl BuchiAutomata {
+    fn from_raw(raw: &RawAutomata, manager: &BddManager, symbols: &HashMap<String, Bdd>) -> Self {
+        let mut ret = Self::new(manager.clone());
+        if raw.num_state > 0 {
+            ret.extend_to(raw.num_state - 1);
+        }
+        ret.init_states = raw.init_states.clone();
+        ret.accepting_states = raw.accepting_states.clone();
+        for (state, dist, edge) in raw.edges.iter() {
+            let edge = if edge == "(1)" {
+                Expr::LitExpr(true)
+            } else {
+                Expr::from(edge.as_str())
+            };
+            ret.add_edge(*state, *dist, edge.to_bdd(manager, symbols));
+        }
+        ret
+    }
 
     pub fn from_ltl(
         ltl: smv::Expr,
         manager: &BddManager,
         symbols: &HashMap<String, usize>,
         defines: &HashMap<String, Bdd>,
+        cache: Option<&str>,
     ) -> Self {
-        println!("'{}'", ltl);
-        let ltl2dfa = Command::new("./ltl2ba/ltl2ba")
-            .arg("-f")
-            .arg(format!("{}", ltl))
-            .output()
-            .unwrap();
-        // let ltl2dfa = Command::new("/root/spot-2.11.5/bin/ltl2tgba")
-        //     .arg("-s")
-        //     .arg("-f")
-        //     .arg(format!("{}", ltl))
-        //     .output()
-        //     .unwrap();
-        let ba = String::from_utf8_lossy(&ltl2dfa.stdout);
+        eprintln!("'{}'", ltl);
+        let raw = translate(&ltl, cache);
         let mut defines = defines.clone();
         for (ident, id) in symbols {
             defines.insert(ident.clone(), manager.ith_var(*id));
         }
-        let mut ba = BuchiAutomata::parse(ba.as_ref(), manager, &defines);
+        let mut ba = BuchiAutomata::from_raw(&raw, manager, &defines);
         dbg!(ba.num_state());
         ba.symbols = symbols.clone();
         ba
     }
 
+    /// Automaton accepting the paths that reach `bad`, if given, and then visit every
+    /// `justice` constraint infinitely often.
+    pub fn from_constraints(
+        manager: &BddManager,
+        symbols: &HashMap<String, usize>,
+        bad: Option<&Bdd>,
+        justice: &[Bdd],
+    ) -> Self {
+        let mut ba = Self::new(manager.clone());
+        ba.symbols = symbols.clone();
+        ba.add_init_state(0);
+        let base = match bad {
+            Some(bad) => {
+                ba.add_edge(0, 0, !bad);
+                ba.add_edge(0, 1, bad.clone());
+                1
+            }
+            None => 0,
+        };
+        let accept = base + justice.len();
+        ba.add_accepting_state(accept);
+        if justice.is_empty() {
+            ba.add_edge(accept, accept, manager.constant(true));
+            return ba;
+        }
+        for i in 0..=justice.len() {
+            let state = base + i;
+            let (wait, next) = if i == justice.len() {
+                (base, base + 1)
+            } else {
+                (state, state + 1)
+            };
+            let j = &justice[i % justice.len()];
+            ba.add_edge(state, wait, !j);
+            ba.add_edge(state, next, j.clone());
+        }
+        ba
+    }
+
     fn automata_state_encode(&self, base: usize, num_encode_var: usize, mut id: usize) -> Bdd {
         let mut res = self.manager.constant(true);
         for i in 0..num_encode_var {
@@ -0,0 +1,249 @@
+use crate::{
+    command::{Algorithm, BenchArgs},
+    cross_check::DISAGREEMENT_EXIT_CODE,
+    property::PropertyIndex,
+};
+use clap::ValueEnum;
+use std::{
+    env, fs, io,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// One benchmark of the manifest. Manifest lines are
+/// `<model> <property> <extend trans> <expected>`, where the extend trans are comma
+/// separated, `-` stands for none or for no expected verdict, and `#` starts a comment.
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub model: String,
+    pub property: PropertyIndex,
+    pub extend_trans: Vec<usize>,
+    pub expected: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub case: usize,
+    pub algorithm: Algorithm,
+    pub threads: usize,
+    pub verdict: String,
+    pub time: Duration,
+    pub mismatch: bool,
+}
+
+pub fn read_manifest(path: &str) -> Result<Vec<Case>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut cases = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 4 {
+            return Err(format!("{}:{}: expected 4 fields", path, number + 1));
+        }
+        let error = |e: std::num::ParseIntError| format!("{}:{}: {}", path, number + 1, e);
+        let extend_trans = if fields[2] == "-" {
+            Vec::new()
+        } else {
+            fields[2]
+                .split(',')
+                .map(|x| x.parse::<usize>().map_err(error))
+                .collect::<Result<_, _>>()?
+        };
+        cases.push(Case {
+            model: fields[0].to_string(),
+            property: fields[1]
+                .parse()
+                .map_err(|e| format!("{}:{}: {}", path, number + 1, e))?,
+            extend_trans,
+            expected: (fields[3] != "-").then(|| fields[3].to_string()),
+        });
+    }
+    Ok(cases)
+}
+
+fn verdict_of_exit_code(code: Option<i32>) -> &'static str {
+    match code {
+        Some(20) => "true",
+        Some(10) => "false",
+        Some(30) => "unknown",
+        Some(DISAGREEMENT_EXIT_CODE) => "disagreement",
+        _ => "error",
+    }
+}
+
+/// Runs case `index` in a child process, killing it after `timeout`. A child that can not
+/// be run is recorded as an error.
+fn run_case(
+    index: usize,
+    case: &Case,
+    algorithm: Algorithm,
+    threads: usize,
+    timeout: Duration,
+) -> Run {
+    let start = Instant::now();
+    let verdict = run_child(case, algorithm, threads, timeout).unwrap_or_else(|e| {
+        eprintln!("{}: {}", case.model, e);
+        "error"
+    });
+    Run {
+        case: index,
+        algorithm,
+        threads,
+        verdict: verdict.to_string(),
+        time: start.elapsed(),
+        mismatch: false,
+    }
+}
+
+fn run_child(
+    case: &Case,
+    algorithm: Algorithm,
+    threads: usize,
+    timeout: Duration,
+) -> io::Result<&'static str> {
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .arg("--model")
+        .arg(&case.model)
+        .arg("--property")
+        .arg(case.property.to_string())
+        .arg("--algorithm")
+        .arg(algorithm.to_possible_value().unwrap().get_name())
+        .arg("--parallel")
+        .arg(threads.to_string());
+    for extend in case.extend_trans.iter() {
+        command.arg("-e").arg(extend.to_string());
+    }
+    let start = Instant::now();
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(verdict_of_exit_code(status.code()));
+        }
+        if start.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok("timeout");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn is_conclusive(verdict: &str) -> bool {
+    verdict == "true" || verdict == "false"
+}
+
+/// Flags runs contradicting the expected verdict, or contradicting another algorithm on the
+/// same case.
+fn flag_mismatches(cases: &[Case], runs: &mut [Run]) {
+    for i in 0..runs.len() {
+        let case = &cases[runs[i].case];
+        let verdict = &runs[i].verdict;
+        let expected = case
+            .expected
+            .as_ref()
+            .is_some_and(|expected| is_conclusive(verdict) && expected != verdict);
+        let disagree = runs.iter().any(|other| {
+            other.case == runs[i].case
+                && is_conclusive(&other.verdict)
+                && is_conclusive(verdict)
+                && other.verdict != *verdict
+        });
+        runs[i].mismatch = expected || disagree;
+    }
+}
+
+fn to_csv(cases: &[Case], runs: &[Run]) -> String {
+    let mut csv = String::from("model,property,algorithm,threads,expected,verdict,time,mismatch");
+    csv.push('\n');
+    for run in runs.iter() {
+        let case = &cases[run.case];
+        csv.push_str(&format!(
+            "{},{},{:?},{},{},{},{:.3},{}\n",
+            case.model,
+            case.property,
+            run.algorithm,
+            run.threads,
+            case.expected.as_deref().unwrap_or("-"),
+            run.verdict,
+            run.time.as_secs_f64(),
+            run.mismatch
+        ));
+    }
+    csv
+}
+
+fn to_json(cases: &[Case], runs: &[Run]) -> String {
+    let rows: Vec<String> = runs
+        .iter()
+        .map(|run| {
+            let case = &cases[run.case];
+            let expected = case
+                .expected
+                .as_ref()
+                .map_or("null".to_string(), |expected| format!("{:?}", expected));
+            format!(
+                "  {{\"model\": {:?}, \"property\": {}, \"algorithm\": \"{:?}\", \
+                 \"threads\": {}, \"expected\": {}, \"verdict\": {:?}, \"time\": {:.3}, \
+                 \"mismatch\": {}}}",
+                case.model,
+                case.property,
+                run.algorithm,
+                run.threads,
+                expected,
+                run.verdict,
+                run.time.as_secs_f64(),
+                run.mismatch
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+/// Runs every case of the manifest with every algorithm and thread count, in manifest
+/// order, and writes the comparison table. Returns whether no mismatch was found.
+pub fn bench(args: &BenchArgs) -> Result<bool, String> {
+    let cases = read_manifest(&args.manifest)?;
+    let timeout = Duration::from_secs(args.timeout);
+    let mut runs = Vec::new();
+    for (i, case) in cases.iter().enumerate() {
+        for algorithm in args.algorithms.iter() {
+            for threads in args.threads.iter() {
+                let run = run_case(i, case, *algorithm, *threads, timeout);
+                println!(
+                    "{} {:?} {}: {} in {:?}",
+                    case.model, algorithm, threads, run.verdict, run.time
+                );
+                runs.push(run);
+            }
+        }
+    }
+    flag_mismatches(&cases, &mut runs);
+    if let Some(path) = &args.csv {
+        fs::write(path, to_csv(&cases, &runs)).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    if let Some(path) = &args.json {
+        fs::write(path, to_json(&cases, &runs)).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    let mismatches: Vec<&Run> = runs.iter().filter(|run| run.mismatch).collect();
+    for run in mismatches.iter() {
+        let case = &cases[run.case];
+        println!(
+            "mismatch: {} property {} {:?} {} threads: {}, expected {}",
+            case.model,
+            case.property,
+            run.algorithm,
+            run.threads,
+            run.verdict,
+            case.expected.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(mismatches.is_empty())
+}
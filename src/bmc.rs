@@ -0,0 +1,308 @@
+use crate::{
+    automata::BuchiAutomata,
+    command::Args,
+    export::dump_automaton,
+    formula::enum_domains,
+    ltl::ltl_automata,
+    order::apply_order_options,
+    partitioned::get_ltl,
+    sat::{Lit, Solver},
+    trace::Trace,
+    verdict::Verdict,
+    Bdd, BddManager,
+};
+use fsmbdd::FsmBdd;
+use smv::{bdd::SmvBdd, Smv};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Encodes the lasso-shaped runs of the product of the model and the Büchi automaton,
+/// one step at a time into a single incremental solver.
+struct Unroller<'a> {
+    fsmbdd: &'a FsmBdd<BddManager>,
+    automata: &'a BuchiAutomata,
+    solver: Solver,
+    constant: Lit,
+    frames: Vec<HashMap<usize, Lit>>,
+    automata_frames: Vec<Vec<Lit>>,
+    /// whether frame `k` is in an accepting automaton state
+    accepting: Vec<Lit>,
+    cache: HashMap<(Bdd, usize), Lit>,
+}
+
+impl<'a> Unroller<'a> {
+    fn new(fsmbdd: &'a FsmBdd<BddManager>, automata: &'a BuchiAutomata) -> Self {
+        let mut solver = Solver::new();
+        let constant = solver.new_var();
+        solver.add_clause(&[constant]);
+        Self {
+            fsmbdd,
+            automata,
+            solver,
+            constant,
+            frames: Vec::new(),
+            automata_frames: Vec::new(),
+            accepting: Vec::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn add_frame(&mut self) {
+        let mut frame = HashMap::new();
+        for var in self.fsmbdd.symbols.values() {
+            frame.insert(*var, self.solver.new_var());
+        }
+        let states: Vec<Lit> = (0..self.automata.num_state())
+            .map(|_| self.solver.new_var())
+            .collect();
+        for i in 0..states.len() {
+            for j in i + 1..states.len() {
+                self.solver.add_clause(&[!states[i], !states[j]]);
+            }
+        }
+        self.frames.push(frame);
+        self.automata_frames.push(states);
+    }
+
+    fn ensure_frame(&mut self, frame: usize) {
+        while self.frames.len() <= frame {
+            self.add_frame();
+        }
+    }
+
+    fn var_lit(&mut self, var: usize, frame: usize) -> Lit {
+        let (state, frame) = if var % 2 == 0 {
+            (var, frame)
+        } else {
+            (var - 1, frame + 1)
+        };
+        self.ensure_frame(frame);
+        *self.frames[frame]
+            .entry(state)
+            .or_insert_with(|| self.solver.new_var())
+    }
+
+    /// Tseitin encoding of a BDD whose current state variables refer to `frame` and whose
+    /// next state variables refer to `frame + 1`.
+    fn bdd_lit(&mut self, bdd: &Bdd, frame: usize) -> Lit {
+        if bdd.is_constant(true) {
+            return self.constant;
+        }
+        if bdd.is_constant(false) {
+            return !self.constant;
+        }
+        if let Some(lit) = self.cache.get(&(bdd.clone(), frame)) {
+            return *lit;
+        }
+        let var = self.var_lit(bdd.var(), frame);
+        let high = self.bdd_lit(&bdd.high(), frame);
+        let low = self.bdd_lit(&bdd.low(), frame);
+        let node = self.solver.new_var();
+        self.solver.add_clause(&[!node, !var, high]);
+        self.solver.add_clause(&[!node, var, low]);
+        self.solver.add_clause(&[node, !var, !high]);
+        self.solver.add_clause(&[node, var, !low]);
+        self.cache.insert((bdd.clone(), frame), node);
+        node
+    }
+
+    fn encode_init(&mut self) {
+        self.ensure_frame(0);
+        let fsmbdd = self.fsmbdd;
+        let init = self.bdd_lit(&fsmbdd.init, 0);
+        self.solver.add_clause(&[init]);
+        self.add_frame_constraint(0);
+        let init_states: Vec<Lit> = self
+            .automata
+            .init_states
+            .iter()
+            .map(|state| self.automata_frames[0][*state])
+            .collect();
+        self.solver.add_clause(&init_states);
+    }
+
+    fn add_frame_constraint(&mut self, frame: usize) {
+        let fsmbdd = self.fsmbdd;
+        let invariants = self.bdd_lit(&fsmbdd.invariants, frame);
+        self.solver.add_clause(&[invariants]);
+    }
+
+    fn encode_step(&mut self, frame: usize) {
+        self.ensure_frame(frame + 1);
+        let fsmbdd = self.fsmbdd;
+        let automata = self.automata;
+        for trans in fsmbdd.trans.trans.iter() {
+            let trans = self.bdd_lit(trans, frame);
+            self.solver.add_clause(&[trans]);
+        }
+        self.add_frame_constraint(frame + 1);
+        for state in 0..automata.num_state() {
+            let mut clause = vec![!self.automata_frames[frame][state]];
+            for (next, label) in automata.forward[state].iter() {
+                let label = self.bdd_lit(label, frame);
+                let next = self.automata_frames[frame + 1][*next];
+                let edge = self.solver.new_var();
+                self.solver.add_clause(&[!edge, label]);
+                self.solver.add_clause(&[!edge, next]);
+                clause.push(edge);
+            }
+            self.solver.add_clause(&clause);
+        }
+    }
+
+    /// Requires the state after `bound` to equal some earlier state with an accepting
+    /// automaton state in between, as long as the returned activation literal is assumed.
+    /// Returns it together with the loop selectors.
+    fn encode_loop(&mut self, bound: usize) -> (Lit, Vec<Lit>) {
+        while self.accepting.len() <= bound {
+            let frame = self.accepting.len();
+            let lit = self.solver.new_var();
+            let mut clause = vec![!lit];
+            for state in self.automata.accepting_states.iter() {
+                clause.push(self.automata_frames[frame][*state]);
+            }
+            self.solver.add_clause(&clause);
+            self.accepting.push(lit);
+        }
+        let vars: Vec<usize> = self.fsmbdd.symbols.values().copied().collect();
+        let mut selectors = Vec::new();
+        for start in 0..=bound {
+            let selector = self.solver.new_var();
+            for var in vars.iter() {
+                let x = self.frames[start][var];
+                let y = self.frames[bound + 1][var];
+                self.solver.add_clause(&[!selector, !x, y]);
+                self.solver.add_clause(&[!selector, x, !y]);
+            }
+            for state in 0..self.automata.num_state() {
+                let x = self.automata_frames[start][state];
+                let y = self.automata_frames[bound + 1][state];
+                self.solver.add_clause(&[!selector, !x, y]);
+                self.solver.add_clause(&[!selector, x, !y]);
+            }
+            let mut clause = vec![!selector];
+            clause.extend_from_slice(&self.accepting[start..=bound]);
+            self.solver.add_clause(&clause);
+            selectors.push(selector);
+        }
+        let active = self.solver.new_var();
+        let mut clause = vec![!active];
+        clause.extend_from_slice(&selectors);
+        self.solver.add_clause(&clause);
+        (active, selectors)
+    }
+
+    fn trace(&self, bound: usize, selectors: &[Lit]) -> Trace {
+        let mut symbols: Vec<(&String, &usize)> = self.fsmbdd.symbols.iter().collect();
+        symbols.sort();
+        let states = (0..=bound)
+            .map(|frame| {
+                symbols
+                    .iter()
+                    .map(|(name, var)| {
+                        let value = self.solver.model_value(self.frames[frame][*var]);
+                        ((*name).clone(), value)
+                    })
+                    .collect()
+            })
+            .collect();
+        let loop_start = selectors
+            .iter()
+            .position(|selector| self.solver.model_value(*selector));
+        Trace {
+            states,
+            loop_start,
+            ..Default::default()
+        }
+    }
+}
+
+/// Searches the shortest lasso-shaped counterexample up to `max_bound`. Every bound only
+/// adds its step to the unrolling of the previous bounds, and enables its loop constraint
+/// by an assumption, so that the solver and its learnt clauses are reused.
+fn bmc(
+    fsmbdd: &FsmBdd<BddManager>,
+    automata: &BuchiAutomata,
+    max_bound: usize,
+    verbose: bool,
+) -> Option<Trace> {
+    let mut unroller = Unroller::new(fsmbdd, automata);
+    unroller.encode_init();
+    for bound in 0..=max_bound {
+        if verbose {
+            dbg!(bound);
+        }
+        unroller.encode_step(bound);
+        let (active, selectors) = unroller.encode_loop(bound);
+        if unroller.solver.solve_with(&[active]) {
+            return Some(unroller.trace(bound, &selectors));
+        }
+        unroller.solver.add_clause(&[!active]);
+    }
+    None
+}
+
+pub fn check(manager: BddManager, smv: Smv, args: Args) -> (Verdict, Duration) {
+    let smv_bdd = SmvBdd::new(&manager, &smv);
+    let fsmbdd = smv_bdd.to_fsmbdd(args.trans_method.into());
+    let (mut fsmbdd, defines) = match apply_order_options(&args, fsmbdd, smv_bdd.defines.clone()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    fsmbdd.justice.clear();
+    let ba = get_ltl(&smv, args.property.index, &args.ltl_extend_trans)
+        .and_then(|ltl| ltl_automata(&smv, &ltl, &mut fsmbdd, &defines, &args));
+    let ba = match ba {
+        Ok(ba) => ba,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    dump_automaton(&args, &ba);
+    eprintln!("bmc begin");
+    let start = Instant::now();
+    if let Some(mut trace) = bmc(&fsmbdd, &ba, args.bmc_bound, args.verbose) {
+        trace.domains = enum_domains(&smv);
+        return (Verdict::Violated(Some(trace)), start.elapsed());
+    }
+    let reason = format!("no counterexample up to bound {}", args.bmc_bound);
+    (Verdict::Unknown(reason), start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aiger::Aiger, property::PropertyIndex};
+    use fsmbdd::TransBddMethod;
+
+    fn bmc_aiger(aag: &str, max_bound: usize) -> Option<Trace> {
+        let manager = crate::test_manager();
+        let aiger = Aiger::parse(aag.as_bytes()).unwrap();
+        let (fsmbdd, properties) = aiger.to_fsmbdd(&manager, TransBddMethod::Partition);
+        let property = PropertyIndex {
+            kind: Some('b'),
+            index: 0,
+        };
+        let ba = properties
+            .automata(&manager, &fsmbdd.symbols, property)
+            .unwrap();
+        bmc(&fsmbdd, &ba, max_bound, false)
+    }
+
+    #[test]
+    fn toggle_reaches_bad() {
+        // the latch toggles from 0 and is bad when 1, the shortest lasso through the
+        // accepting sink of the automaton needs bound 3
+        let aag = "aag 1 0 1 0 0 1\n2 3\n2\n";
+        assert!(bmc_aiger(aag, 2).is_none());
+        let trace = bmc_aiger(aag, 3).unwrap();
+        assert_eq!(trace.states.len(), 4);
+        assert_eq!(trace.states[0], vec![("l0".to_string(), false)]);
+        assert_eq!(trace.states[1], vec![("l0".to_string(), true)]);
+    }
+
+    #[test]
+    fn stuck_latch_is_safe() {
+        assert!(bmc_aiger("aag 1 0 1 0 0 1\n2 2\n2\n", 6).is_none());
+    }
+}
@@ -0,0 +1,445 @@
+use crate::{property::Properties, Bdd, BddManager};
+use fsmbdd::{FsmBdd, Trans, TransBddMethod};
+use std::{collections::HashMap, fs};
+
+pub fn is_btor2(path: &str) -> bool {
+    path.ends_with(".btor2") || path.ends_with(".btor")
+}
+
+fn xor(a: &Bdd, b: &Bdd) -> Bdd {
+    (a & !b) | (!a & b)
+}
+
+fn xnor(a: &Bdd, b: &Bdd) -> Bdd {
+    (a & b) | (!a & !b)
+}
+
+fn ite(c: &Bdd, a: &Bdd, b: &Bdd) -> Bdd {
+    (c & a) | (!c & b)
+}
+
+/// Bit-blasts the bitvector operators of a BTOR2 model, least significant bit first.
+struct Blaster<'a> {
+    manager: &'a BddManager,
+    sorts: HashMap<usize, usize>,
+    nodes: HashMap<usize, Vec<Bdd>>,
+}
+
+impl<'a> Blaster<'a> {
+    fn constant(&self, value: bool) -> Bdd {
+        self.manager.constant(value)
+    }
+
+    fn width(&self, sid: &str) -> Result<usize, String> {
+        let sid = sid.parse::<usize>().map_err(|e| e.to_string())?;
+        self.sorts
+            .get(&sid)
+            .copied()
+            .ok_or_else(|| format!("unknown or unsupported btor2 sort {}", sid))
+    }
+
+    fn node(&self, id: &str) -> Result<Vec<Bdd>, String> {
+        let id = id.parse::<i64>().map_err(|e| e.to_string())?;
+        let bits = self
+            .nodes
+            .get(&(id.unsigned_abs() as usize))
+            .ok_or_else(|| format!("unknown btor2 node {}", id))?;
+        Ok(if id < 0 {
+            bits.iter().map(|bit| !bit).collect()
+        } else {
+            bits.clone()
+        })
+    }
+
+    fn bits_of_binary(&self, binary: &str) -> Vec<Bdd> {
+        binary
+            .chars()
+            .rev()
+            .map(|c| self.constant(c == '1'))
+            .collect()
+    }
+
+    fn bits_of_decimal(&self, decimal: &str, width: usize) -> Result<Vec<Bdd>, String> {
+        let negative = decimal.starts_with('-');
+        let digits: Option<Vec<u32>> = decimal
+            .trim_start_matches('-')
+            .chars()
+            .map(|c| c.to_digit(10))
+            .collect();
+        let mut digits =
+            digits.ok_or_else(|| format!("invalid btor2 decimal constant '{}'", decimal))?;
+        let mut bits = Vec::new();
+        for _ in 0..width {
+            let mut rem = 0;
+            for digit in digits.iter_mut() {
+                let x = rem * 10 + *digit;
+                *digit = x / 2;
+                rem = x % 2;
+            }
+            bits.push(rem == 1);
+        }
+        if negative {
+            bits.iter_mut().for_each(|bit| *bit = !*bit);
+            for bit in bits.iter_mut() {
+                *bit = !*bit;
+                if *bit {
+                    break;
+                }
+            }
+        }
+        Ok(bits.into_iter().map(|bit| self.constant(bit)).collect())
+    }
+
+    fn bits_of_hex(&self, hex: &str, width: usize) -> Result<Vec<Bdd>, String> {
+        let mut bits = Vec::new();
+        for c in hex.chars().rev() {
+            let x = c
+                .to_digit(16)
+                .ok_or_else(|| format!("invalid btor2 hexadecimal constant '{}'", hex))?;
+            for i in 0..4 {
+                bits.push(x >> i & 1 == 1);
+            }
+        }
+        bits.resize(width, false);
+        Ok(bits.into_iter().map(|bit| self.constant(bit)).collect())
+    }
+
+    fn add(&self, a: &[Bdd], b: &[Bdd], mut carry: Bdd) -> Vec<Bdd> {
+        let mut sum = Vec::new();
+        for (x, y) in a.iter().zip(b.iter()) {
+            sum.push(xor(&xor(x, y), &carry));
+            carry = (x & y) | (&carry & &xor(x, y));
+        }
+        sum
+    }
+
+    fn neg(&self, a: &[Bdd]) -> Vec<Bdd> {
+        let not: Vec<Bdd> = a.iter().map(|bit| !bit).collect();
+        let zero = vec![self.constant(false); a.len()];
+        self.add(&not, &zero, self.constant(true))
+    }
+
+    fn mul(&self, a: &[Bdd], b: &[Bdd]) -> Vec<Bdd> {
+        let mut product = vec![self.constant(false); a.len()];
+        for (i, y) in b.iter().enumerate() {
+            let mut partial = vec![self.constant(false); i];
+            partial.extend(a.iter().take(a.len() - i).map(|x| x & y));
+            product = self.add(&product, &partial, self.constant(false));
+        }
+        product
+    }
+
+    fn eq(&self, a: &[Bdd], b: &[Bdd]) -> Bdd {
+        a.iter()
+            .zip(b.iter())
+            .fold(self.constant(true), |eq, (x, y)| eq & xnor(x, y))
+    }
+
+    fn ult(&self, a: &[Bdd], b: &[Bdd]) -> Bdd {
+        a.iter()
+            .zip(b.iter())
+            .fold(self.constant(false), |lt, (x, y)| (!x & y) | (xnor(x, y) & lt))
+    }
+
+    fn slt(&self, a: &[Bdd], b: &[Bdd]) -> Bdd {
+        let flip = |bits: &[Bdd]| {
+            let mut bits = bits.to_vec();
+            let msb = bits.pop().unwrap();
+            bits.push(!msb);
+            bits
+        };
+        self.ult(&flip(a), &flip(b))
+    }
+
+    fn shift(&self, a: &[Bdd], b: &[Bdd], left: bool, fill: &Bdd) -> Vec<Bdd> {
+        let mut res = a.to_vec();
+        let width = a.len();
+        let mut overflow = self.constant(false);
+        for (i, s) in b.iter().enumerate() {
+            let amount = 1usize.checked_shl(i as u32).unwrap_or(usize::MAX);
+            if amount >= width {
+                overflow |= s;
+                continue;
+            }
+            let shifted: Vec<Bdd> = (0..width)
+                .map(|j| {
+                    if left {
+                        if j >= amount {
+                            res[j - amount].clone()
+                        } else {
+                            self.constant(false)
+                        }
+                    } else if j + amount < width {
+                        res[j + amount].clone()
+                    } else {
+                        fill.clone()
+                    }
+                })
+                .collect();
+            res = res
+                .iter()
+                .zip(shifted.iter())
+                .map(|(x, y)| ite(s, y, x))
+                .collect();
+        }
+        let fill = if left { self.constant(false) } else { fill.clone() };
+        res.iter().map(|x| ite(&overflow, &fill, x)).collect()
+    }
+
+    fn binary(&self, op: &str, a: &[Bdd], b: &[Bdd]) -> Result<Vec<Bdd>, String> {
+        let bitwise = |f: fn(&Bdd, &Bdd) -> Bdd| -> Vec<Bdd> {
+            a.iter().zip(b.iter()).map(|(x, y)| f(x, y)).collect()
+        };
+        Ok(match op {
+            "and" => bitwise(|x, y| x & y),
+            "or" => bitwise(|x, y| x | y),
+            "xor" => bitwise(xor),
+            "nand" => bitwise(|x, y| !(x & y)),
+            "nor" => bitwise(|x, y| !(x | y)),
+            "xnor" | "iff" => bitwise(xnor),
+            "implies" => bitwise(|x, y| !x | y),
+            "add" => self.add(a, b, self.constant(false)),
+            "sub" => self.add(a, &self.neg(b), self.constant(false)),
+            "mul" => self.mul(a, b),
+            "eq" => vec![self.eq(a, b)],
+            "neq" => vec![!self.eq(a, b)],
+            "ult" => vec![self.ult(a, b)],
+            "ulte" => vec![!self.ult(b, a)],
+            "ugt" => vec![self.ult(b, a)],
+            "ugte" => vec![!self.ult(a, b)],
+            "slt" => vec![self.slt(a, b)],
+            "slte" => vec![!self.slt(b, a)],
+            "sgt" => vec![self.slt(b, a)],
+            "sgte" => vec![!self.slt(a, b)],
+            "concat" => b.iter().chain(a.iter()).cloned().collect(),
+            "sll" => self.shift(a, b, true, &self.constant(false)),
+            "srl" => self.shift(a, b, false, &self.constant(false)),
+            "sra" => self.shift(a, b, false, a.last().unwrap()),
+            _ => return Err(format!("unsupported btor2 operator '{}'", op)),
+        })
+    }
+
+    fn unary(&self, op: &str, a: &[Bdd]) -> Result<Vec<Bdd>, String> {
+        let one = |width: usize| {
+            let mut one = vec![self.constant(false); width];
+            one[0] = self.constant(true);
+            one
+        };
+        Ok(match op {
+            "not" => a.iter().map(|x| !x).collect(),
+            "inc" => self.add(a, &one(a.len()), self.constant(false)),
+            "dec" => self.add(a, &vec![self.constant(true); a.len()], self.constant(false)),
+            "neg" => self.neg(a),
+            "redand" => vec![a.iter().fold(self.constant(true), |r, x| r & x)],
+            "redor" => vec![a.iter().fold(self.constant(false), |r, x| r | x)],
+            "redxor" => vec![a.iter().fold(self.constant(false), |r, x| xor(&r, x))],
+            _ => return Err(format!("unsupported btor2 operator '{}'", op)),
+        })
+    }
+}
+
+/// Bit-blasts a BTOR2 model. A bitvector named `x` of width `w > 1` is represented by
+/// the boolean symbols `x[0]`, ..., `x[w-1]`.
+pub fn parse(
+    source: &str,
+    manager: &BddManager,
+    method: TransBddMethod,
+) -> Result<(FsmBdd<BddManager>, Properties), String> {
+    let mut blaster = Blaster {
+        manager,
+        sorts: HashMap::new(),
+        nodes: HashMap::new(),
+    };
+    let mut symbols = HashMap::new();
+    let mut init = manager.constant(true);
+    let mut trans = Vec::new();
+    let mut invariants = manager.constant(true);
+    let mut properties = Properties::default();
+    for line in source.lines() {
+        let line = line.split(';').next().unwrap();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let id = tokens[0].parse::<usize>().map_err(|e| e.to_string())?;
+        let op = *tokens
+            .get(1)
+            .ok_or_else(|| format!("missing operator for node {}", id))?;
+        let arg = |i: usize| {
+            tokens
+                .get(i)
+                .copied()
+                .ok_or_else(|| format!("missing argument {} of node {}", i, id))
+        };
+        let bits = match op {
+            "sort" => {
+                if arg(2)? != "bitvec" {
+                    return Err(format!("unsupported btor2 sort '{}'", arg(2)?));
+                }
+                let width = arg(3)?.parse::<usize>().map_err(|e| e.to_string())?;
+                blaster.sorts.insert(id, width);
+                continue;
+            }
+            "input" | "state" => {
+                let width = blaster.width(arg(2)?)?;
+                let name = tokens
+                    .get(3)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("{}{}", op, id));
+                (0..width)
+                    .map(|i| {
+                        let var = symbols.len() * 2;
+                        let bit = if width == 1 {
+                            name.clone()
+                        } else {
+                            format!("{}[{}]", name, i)
+                        };
+                        symbols.insert(bit, var);
+                        manager.ith_var(var + 1);
+                        manager.ith_var(var)
+                    })
+                    .collect()
+            }
+            "init" | "next" => {
+                let state = blaster.node(arg(3)?)?;
+                let value = blaster.node(arg(4)?)?;
+                for (s, v) in state.iter().zip(value.iter()) {
+                    if op == "init" {
+                        init &= xnor(s, v);
+                    } else {
+                        trans.push(xnor(&s.next_state(), v));
+                    }
+                }
+                continue;
+            }
+            "bad" | "constraint" | "fair" | "output" => {
+                let bit = blaster.node(arg(2)?)?[0].clone();
+                match op {
+                    "bad" => properties.bad.push(bit),
+                    "constraint" => invariants &= bit,
+                    "fair" => properties.fairness.push(bit),
+                    _ => (),
+                }
+                continue;
+            }
+            "justice" => {
+                let num = arg(2)?.parse::<usize>().map_err(|e| e.to_string())?;
+                let justice = (0..num)
+                    .map(|i| Ok(blaster.node(arg(3 + i)?)?[0].clone()))
+                    .collect::<Result<Vec<Bdd>, String>>()?;
+                properties.justice.push(justice);
+                continue;
+            }
+            "const" => blaster.bits_of_binary(arg(3)?),
+            "constd" => blaster.bits_of_decimal(arg(3)?, blaster.width(arg(2)?)?)?,
+            "consth" => blaster.bits_of_hex(arg(3)?, blaster.width(arg(2)?)?)?,
+            "zero" | "one" | "ones" => {
+                let width = blaster.width(arg(2)?)?;
+                let mut bits = vec![manager.constant(op == "ones"); width];
+                if op == "one" {
+                    bits[0] = manager.constant(true);
+                }
+                bits
+            }
+            "ite" => {
+                let c = blaster.node(arg(3)?)?[0].clone();
+                let a = blaster.node(arg(4)?)?;
+                let b = blaster.node(arg(5)?)?;
+                a.iter().zip(b.iter()).map(|(x, y)| ite(&c, x, y)).collect()
+            }
+            "slice" => {
+                let a = blaster.node(arg(3)?)?;
+                let upper = arg(4)?.parse::<usize>().map_err(|e| e.to_string())?;
+                let lower = arg(5)?.parse::<usize>().map_err(|e| e.to_string())?;
+                if lower > upper || upper >= a.len() {
+                    return Err(format!("invalid btor2 slice [{}:{}]", upper, lower));
+                }
+                a[lower..=upper].to_vec()
+            }
+            "uext" | "sext" => {
+                let mut a = blaster.node(arg(3)?)?;
+                let extend = arg(4)?.parse::<usize>().map_err(|e| e.to_string())?;
+                let fill = if op == "sext" {
+                    a.last().unwrap().clone()
+                } else {
+                    manager.constant(false)
+                };
+                a.extend(std::iter::repeat(fill).take(extend));
+                a
+            }
+            "not" | "inc" | "dec" | "neg" | "redand" | "redor" | "redxor" => {
+                blaster.unary(op, &blaster.node(arg(3)?)?)?
+            }
+            _ => {
+                let a = blaster.node(arg(3)?)?;
+                let b = blaster.node(arg(4)?)?;
+                blaster.binary(op, &a, &b)?
+            }
+        };
+        blaster.nodes.insert(id, bits);
+    }
+    let fsmbdd = FsmBdd {
+        symbols,
+        manager: manager.clone(),
+        init,
+        invariants,
+        trans: Trans::new(manager, trans, method),
+        justice: Vec::new(),
+    };
+    Ok((fsmbdd, properties))
+}
+
+pub fn from_file(
+    path: &str,
+    manager: &BddManager,
+    method: TransBddMethod,
+) -> Result<(FsmBdd<BddManager>, Properties), String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse(&source, manager, method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two bit counter from 0, bad when it reaches 3.
+    const COUNTER: &str = "\
+1 sort bitvec 2
+2 sort bitvec 1
+3 state 1 s
+4 zero 1
+5 init 1 3 4
+6 one 1
+7 add 1 3 6
+8 next 1 3 7
+9 constd 1 3
+10 eq 2 3 9
+11 bad 10 ; reaches 3
+";
+
+    #[test]
+    fn counter() {
+        let manager = crate::test_manager();
+        let (fsmbdd, properties) = parse(COUNTER, &manager, TransBddMethod::Partition).unwrap();
+        assert_eq!(fsmbdd.symbols.len(), 2);
+        let s0 = manager.ith_var(fsmbdd.symbols["s[0]"]);
+        let s1 = manager.ith_var(fsmbdd.symbols["s[1]"]);
+        assert!(fsmbdd.init == !&s0 & !&s1);
+        assert!(properties.bad == vec![&s0 & &s1]);
+        assert!(fsmbdd.post_image(&fsmbdd.init) == &s0 & !&s1);
+        assert!(fsmbdd.post_image(&(&s0 & &s1)) == fsmbdd.init);
+    }
+
+    #[test]
+    fn errors() {
+        let manager = crate::test_manager();
+        let fails = |source: &str| parse(source, &manager, TransBddMethod::Partition).is_err();
+        assert!(fails("1 sort array 2 3\n"));
+        assert!(fails("1 sort bitvec 1\n2 state 1\n3 foo 1 2 2\n"));
+        assert!(fails("1 state 7\n"));
+        assert!(fails("1 sort bitvec 2\n2 constd 1 1x\n"));
+        assert!(fails("1 sort bitvec 2\n2 consth 1 g\n"));
+        let slice = "1 sort bitvec 2\n2 sort bitvec 1\n3 zero 1\n4 slice 2 3 2 2\n";
+        assert!(fails(slice));
+    }
+}
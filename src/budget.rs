@@ -0,0 +1,76 @@
+use crate::command::Args;
+use std::{
+    fmt::Display,
+    fs,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Time,
+    Memory,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::Time => write!(f, "time limit exceeded"),
+            LimitExceeded::Memory => write!(f, "memory limit exceeded"),
+        }
+    }
+}
+
+/// Resident set size of this process in bytes, as reported by the kernel in kB so that
+/// the page size does not matter.
+fn resident_memory() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb << 10)
+}
+
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Starts the budget clock. Called first thing in main so that parsing the model and
+/// building the automaton count against the time limit.
+pub fn start() {
+    START.get_or_init(Instant::now);
+}
+
+/// Time and memory budget of a run, checked between fixpoint iterations.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    start: Instant,
+    time_limit: Option<Duration>,
+    memory_limit: Option<usize>,
+}
+
+impl Budget {
+    pub fn new(args: &Args) -> Self {
+        Self {
+            start: *START.get_or_init(Instant::now),
+            time_limit: args.time_limit.map(Duration::from_secs),
+            memory_limit: args.memory_limit.map(|mb| mb << 20),
+        }
+    }
+
+    pub fn has_memory_limit(&self) -> bool {
+        self.memory_limit.is_some()
+    }
+
+    pub fn check(&self) -> Result<(), LimitExceeded> {
+        if self
+            .time_limit
+            .is_some_and(|limit| self.start.elapsed() > limit)
+        {
+            return Err(LimitExceeded::Time);
+        }
+        if let Some(limit) = self.memory_limit {
+            if resident_memory().is_some_and(|memory| memory > limit) {
+                return Err(LimitExceeded::Memory);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    automata::{ltl2ba, RawAutomata, LTL2BA},
+    export::Hoa,
+};
+use std::{collections::HashMap, fs, path::Path, time::UNIX_EPOCH};
+
+/// FNV-1a, which unlike the std hasher is stable across builds.
+fn fnv1a(data: &str) -> u64 {
+    data.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Identity of the translator binary, so that the cache is invalidated when it changes.
+fn translator_identity() -> String {
+    let modified = fs::metadata(LTL2BA)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_secs());
+    format!("{} {}", LTL2BA, modified)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '[' | ']' | '$' | '#')
+}
+
+/// Splits an ltl2ba or HOA label into identifiers and operators.
+fn tokenize(label: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = label.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if is_ident_char(c) {
+            let mut ident = c.to_string();
+            while let Some(c) = chars.next_if(|c| is_ident_char(*c)) {
+                ident.push(c);
+            }
+            tokens.push(ident);
+        } else if (c == '&' || c == '|') && chars.next_if_eq(&c).is_some() {
+            tokens.push(format!("{}{}", c, c));
+        } else {
+            tokens.push(c.to_string());
+        }
+    }
+    tokens
+}
+
+/// Joins label tokens, with spaces around binary operators only.
+fn join_tokens(tokens: Vec<String>) -> String {
+    tokens
+        .into_iter()
+        .map(|token| match token.as_str() {
+            "&" | "|" | "&&" | "||" => format!(" {} ", token),
+            _ => token,
+        })
+        .collect()
+}
+
+fn label_to_hoa(label: &str, aps: &mut Vec<String>) -> String {
+    if label == "(1)" {
+        return "t".to_string();
+    }
+    let tokens = tokenize(label)
+        .into_iter()
+        .map(|token| match token.as_str() {
+            "&&" => "&".to_string(),
+            "||" => "|".to_string(),
+            "true" | "1" => "t".to_string(),
+            "false" | "0" => "f".to_string(),
+            "(" | ")" | "!" => token,
+            _ => {
+                let index = aps.iter().position(|ap| *ap == token).unwrap_or_else(|| {
+                    aps.push(token);
+                    aps.len() - 1
+                });
+                index.to_string()
+            }
+        })
+        .collect();
+    join_tokens(tokens)
+}
+
+fn label_from_hoa(label: &str, aps: &[String]) -> Option<String> {
+    if label.trim() == "t" {
+        return Some("(1)".to_string());
+    }
+    tokenize(label)
+        .into_iter()
+        .map(|token| match token.as_str() {
+            "&" => Some("&&".to_string()),
+            "|" => Some("||".to_string()),
+            "t" => Some("TRUE".to_string()),
+            "f" => Some("FALSE".to_string()),
+            "(" | ")" | "!" => Some(token),
+            _ => aps.get(token.parse::<usize>().ok()?).cloned(),
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(join_tokens)
+}
+
+fn to_hoa(raw: &RawAutomata, name: &str, tool: &str) -> String {
+    let mut aps = Vec::new();
+    let mut edges = vec![Vec::new(); raw.num_state];
+    for (state, next, label) in raw.edges.iter() {
+        edges[*state].push((label_to_hoa(label, &mut aps), *next));
+    }
+    Hoa {
+        headers: vec![("tool", tool.to_string()), ("name", name.to_string())],
+        init_states: raw.init_states.clone(),
+        aps,
+        accepting_states: raw.accepting_states.clone(),
+        edges,
+    }
+    .to_string()
+}
+
+/// Reads an automaton written by `to_hoa`, if it was translated from `name` by `tool`.
+fn from_hoa(hoa: &str, name: &str, tool: &str) -> Option<RawAutomata> {
+    let (header, body) = hoa.split_once("--BODY--")?;
+    let mut headers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for line in header.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.entry(key.trim()).or_default().push(value.trim());
+        }
+    }
+    let header = |key: &str| headers.get(key).and_then(|values| values.first()).copied();
+    if header("tool")? != format!("{:?}", tool) || header("name")? != format!("{:?}", name) {
+        return None;
+    }
+    let mut raw = RawAutomata {
+        num_state: header("States")?.parse().ok()?,
+        ..Default::default()
+    };
+    for start in headers.get("Start")?.iter() {
+        raw.init_states.push(start.parse().ok()?);
+    }
+    let aps: Vec<String> = header("AP")?
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(|ap| ap.to_string())
+        .collect();
+    let mut state = None;
+    for line in body.lines().map(|line| line.trim()) {
+        if let Some(rest) = line.strip_prefix("State:") {
+            let mut fields = rest.split_whitespace();
+            let id: usize = fields.next()?.parse().ok()?;
+            if fields.next() == Some("{0}") {
+                raw.accepting_states.push(id);
+            }
+            state = Some(id);
+        } else if let Some(rest) = line.strip_prefix('[') {
+            let (label, next) = rest.split_once(']')?;
+            let label = label_from_hoa(label, &aps)?;
+            raw.edges.push((state?, next.trim().parse().ok()?, label));
+        }
+    }
+    Some(raw)
+}
+
+/// Translates `ltl` into an automaton, reusing the HOA file of a previous translation of
+/// the same formula by the same translator in the `cache` directory, if given.
+pub fn translate(ltl: &smv::Expr, cache: Option<&str>) -> RawAutomata {
+    let Some(cache) = cache else {
+        return RawAutomata::from_never_claim(&ltl2ba(ltl));
+    };
+    let name = ltl.to_string();
+    let tool = translator_identity();
+    let key = fnv1a(&format!("{}\n{}", tool, name));
+    let path = Path::new(cache).join(format!("{:016x}.hoa", key));
+    if let Some(raw) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|hoa| from_hoa(&hoa, &name, &tool))
+    {
+        eprintln!("translation cache hit {}", path.display());
+        return raw;
+    }
+    let raw = RawAutomata::from_never_claim(&ltl2ba(ltl));
+    let written =
+        fs::create_dir_all(cache).and_then(|_| fs::write(&path, to_hoa(&raw, &name, &tool)));
+    if let Err(e) = written {
+        eprintln!(
+            "can not write the translation cache {}: {}",
+            path.display(),
+            e
+        );
+    }
+    raw
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hoa_round_trip() {
+        let label = |label: &str| label.to_string();
+        let raw = RawAutomata {
+            num_state: 3,
+            init_states: vec![0],
+            accepting_states: vec![2],
+            edges: vec![
+                (0, 0, label("(1)")),
+                (0, 1, label("(!p0 && p1)")),
+                (1, 2, label("(p1) || (p0 && !p1)")),
+                (2, 2, label("(1)")),
+            ],
+        };
+        let hoa = to_hoa(&raw, "F G p0", "ltl2ba 0");
+        let read = from_hoa(&hoa, "F G p0", "ltl2ba 0").unwrap();
+        assert_eq!(read.num_state, raw.num_state);
+        assert_eq!(read.init_states, raw.init_states);
+        assert_eq!(read.accepting_states, raw.accepting_states);
+        assert_eq!(read.edges, raw.edges);
+        assert!(from_hoa(&hoa, "G F p0", "ltl2ba 0").is_none());
+        assert!(from_hoa(&hoa, "F G p0", "ltl2ba 1").is_none());
+    }
+
+    #[test]
+    fn constants_in_labels() {
+        let aps = ["p0".to_string()];
+        assert_eq!(label_from_hoa(" t ", &aps).as_deref(), Some("(1)"));
+        assert_eq!(label_from_hoa("0 & t", &aps).as_deref(), Some("p0 && TRUE"));
+        assert_eq!(
+            label_from_hoa("!f | 0", &aps).as_deref(),
+            Some("!FALSE || p0")
+        );
+        assert_eq!(label_from_hoa("1", &aps), None);
+    }
+}
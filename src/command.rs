@@ -1,4 +1,5 @@
-use clap::{Parser, ValueEnum};
+use crate::property::PropertyIndex;
+use clap::{Parser, Subcommand, ValueEnum};
 use fsmbdd::TransBddMethod;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -11,6 +12,17 @@ pub enum TransMethod {
 pub enum Algorithm {
     Partitioned,
     Traditional,
+    Bmc,
+    LivenessToSafety,
+    Ctl,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ParallelMode {
+    /// images of all partitions on the shared manager, one after another
+    Sequential,
+    /// lace tasks synchronised every iteration
+    Lace,
 }
 
 impl Into<TransBddMethod> for TransMethod {
@@ -22,9 +34,54 @@ impl Into<TransBddMethod> for TransMethod {
     }
 }
 
-#[derive(Parser, Debug)]
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// run the benchmarks of a manifest and compare the verdicts
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// manifest file, one `<model> <property> <extend trans> <expected>` per line
+    pub manifest: String,
+
+    /// algorithms to run on every benchmark
+    #[arg(short = 'a', long, value_enum, value_delimiter = ',')]
+    #[arg(default_values_t = [Algorithm::Partitioned, Algorithm::Traditional])]
+    pub algorithms: Vec<Algorithm>,
+
+    /// thread counts to run every benchmark with
+    #[arg(short = 'p', long, value_delimiter = ',', default_values_t = [1])]
+    pub threads: Vec<usize>,
+
+    /// timeout of each run in seconds
+    #[arg(long, default_value_t = 3600)]
+    pub timeout: u64,
+
+    /// write the results as csv
+    #[arg(long)]
+    pub csv: Option<String>,
+
+    /// write the results as json
+    #[arg(long)]
+    pub json: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
 /// Partitioned Symbolic Model Checking
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// model file, smv, aiger or btor2
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// index of the checked property, `b<n>` or `j<n>` for a bad or justice property of a
+    /// hardware model
+    #[arg(long, default_value = "0")]
+    pub property: PropertyIndex,
+
     /// model checking algorithm
     #[arg(short = 'a', long, value_enum, default_value_t = Algorithm::Partitioned)]
     pub algorithm: Algorithm,
@@ -41,10 +98,92 @@ pub struct Args {
     #[arg(short = 'e', long)]
     pub ltl_extend_trans: Vec<usize>,
 
-    /// lace optimize
-    #[arg(long, default_value_t = false)]
+    /// parallel mode
+    #[arg(long, value_enum, default_value_t = ParallelMode::Lace)]
+    pub parallel_mode: ParallelMode,
+
+    /// lace optimize, the same as --parallel-mode sequential
+    #[arg(long, default_value_t = false, conflicts_with = "parallel_mode")]
     pub close_lace_optimize: bool,
 
+    /// simplify frontiers with reached states as don't cares before imaging
+    #[arg(long, default_value_t = false)]
+    pub frontier_simplify: bool,
+
+    /// prune partitions with an over-approximated forward reachability
+    #[arg(long, default_value_t = false)]
+    pub approx_prune: bool,
+
+    /// compute fair states without forward reachability constraint
+    #[arg(long, default_value_t = false)]
+    pub backward_only: bool,
+
+    /// check the safety part of the automaton with the fair cycles instead of during the
+    /// post reachability
+    #[arg(long, default_value_t = false)]
+    pub no_safety_split: bool,
+
+    /// asynchronous post reachability, imaging a partition whenever new states arrive in it
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["frontier_simplify", "dynamic_reorder"]
+    )]
+    pub async_reach: bool,
+
+    /// ctl specification, in addition to the SPEC and CTLSPEC of the model
+    #[arg(long)]
+    pub ctl: Vec<String>,
+
+    /// read the variable order from a NuSMV order file
+    #[arg(long)]
+    pub order_file: Option<String>,
+
+    /// write the variable order to a NuSMV order file
+    #[arg(long)]
+    pub output_order_file: Option<String>,
+
+    /// static variable ordering with the FORCE heuristic
+    #[arg(long, default_value_t = false)]
+    pub force_order: bool,
+
+    /// dynamic variable reordering between fixpoint iterations
+    #[arg(long, default_value_t = false)]
+    pub dynamic_reorder: bool,
+
+    /// independent FORCE variable order for each partition worker
+    #[arg(long, default_value_t = false)]
+    pub worker_order: bool,
+
+    /// memory limit in megabytes, checked between fixpoint iterations
+    #[arg(long)]
+    pub memory_limit: Option<usize>,
+
+    /// time limit in seconds, checked between fixpoint iterations
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+
+    /// write the automaton of the property to a file, in HOA if it ends with .hoa and in
+    /// DOT otherwise
+    #[arg(long)]
+    pub dump_automaton: Option<String>,
+
+    /// directory caching the automata translated from ltl formulas, in HOA
+    #[arg(long)]
+    pub translation_cache: Option<String>,
+
+    /// run both the partitioned and the traditional algorithm and compare their verdicts
+    #[arg(long, default_value_t = false)]
+    pub cross_check: bool,
+
+    /// also compare the monolithic and partition trans methods in the cross check
+    #[arg(long, default_value_t = false)]
+    pub cross_check_trans_method: bool,
+
+    /// bmc bound
+    #[arg(long, default_value_t = 20)]
+    pub bmc_bound: usize,
+
     /// verbose
     #[arg(short, long, default_value_t = false)]
     pub verbose: bool,
@@ -0,0 +1,89 @@
+use crate::{
+    automata::ltl2ba,
+    command::{Args, TransMethod},
+    ltl::abstract_propositions,
+    partitioned::{self, get_ltl},
+    rewrite::rewrite_ltl,
+    traditional,
+    verdict::Verdict,
+    BddManager,
+};
+use smv::{bdd::SmvBdd, Expr, Smv};
+use std::time::Duration;
+
+/// Exit code of a run whose algorithms disagree.
+pub const DISAGREEMENT_EXIT_CODE: i32 = 40;
+
+fn parse(path: &str) -> Result<Smv, String> {
+    Smv::from_file(path).map_err(|e| format!("{}: {:?}", path, e))
+}
+
+/// Prints the specification and the automata given to both algorithms.
+fn report_disagreement(manager: &BddManager, smv: &Smv, args: &Args) -> Result<(), String> {
+    eprintln!("ltl spec: {}", smv.ltlspecs[args.property.index]);
+    eprintln!("extend trans: {:?}", args.ltl_extend_trans);
+    eprintln!("fairness: {:?}", smv.fairness);
+    let smv_bdd = SmvBdd::new(manager, smv);
+    let translate = |ltl: &Expr| {
+        let (ltl, _) = abstract_propositions(smv, ltl, manager, &smv_bdd.symbols, &smv_bdd.defines);
+        rewrite_ltl(&ltl)
+    };
+    let ltl = translate(&get_ltl(smv, args.property.index, &args.ltl_extend_trans)?);
+    eprintln!("partitioned automaton of '{}':\n{}", ltl, ltl2ba(&ltl));
+    let ltl = translate(&!smv.ltlspecs[args.property.index].clone());
+    eprintln!("traditional automaton of '{}':\n{}", ltl, ltl2ba(&ltl));
+    Ok(())
+}
+
+/// Checks the model with both the partitioned and the traditional algorithm, and with both
+/// trans methods if asked, reporting any disagreement between their verdicts together with
+/// the automata they were given. None when the verdicts disagree.
+pub fn check(manager: BddManager, path: &str, args: Args) -> Option<(Verdict, Duration)> {
+    let mut methods = vec![args.trans_method];
+    if args.cross_check_trans_method {
+        methods.push(match args.trans_method {
+            TransMethod::Monolithic => TransMethod::Partition,
+            TransMethod::Partition => TransMethod::Monolithic,
+        });
+    }
+    let mut results = Vec::new();
+    let mut time = Duration::ZERO;
+    for method in methods {
+        let mut args = args.clone();
+        args.trans_method = method;
+        let (partitioned_smv, traditional_smv) = match (parse(path), parse(path)) {
+            (Ok(partitioned_smv), Ok(traditional_smv)) => (partitioned_smv, traditional_smv),
+            (Err(error), _) | (_, Err(error)) => return Some((Verdict::Unknown(error), time)),
+        };
+        let (verdict, partitioned_time) =
+            partitioned::check(manager.clone(), partitioned_smv, args.clone());
+        results.push((format!("partitioned {:?}", method), verdict));
+        let (verdict, traditional_time) =
+            traditional::check(manager.clone(), traditional_smv, args);
+        results.push((format!("traditional {:?}", method), verdict));
+        time += partitioned_time + traditional_time;
+    }
+    for (name, verdict) in results.iter() {
+        eprintln!("cross check: {}: {}", name, verdict);
+    }
+    let conclusive: Vec<&(String, Verdict)> = results
+        .iter()
+        .filter(|(_, verdict)| !matches!(verdict, Verdict::Unknown(_)))
+        .collect();
+    let disagree = conclusive
+        .windows(2)
+        .any(|pair| pair[0].1.exit_code() != pair[1].1.exit_code());
+    if disagree {
+        eprintln!("!!! cross check disagreement !!!");
+        let report = parse(path).and_then(|smv| report_disagreement(&manager, &smv, &args));
+        if let Err(error) = report {
+            eprintln!("cross check: {}", error);
+        }
+        return None;
+    }
+    let verdict = conclusive.first().map_or_else(
+        || results[0].1.clone(),
+        |(_, verdict)| verdict.clone(),
+    );
+    Some((verdict, time))
+}
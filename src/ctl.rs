@@ -0,0 +1,604 @@
+use crate::{
+    command::{Args, ParallelMode},
+    formula::enum_domains,
+    ltl::{ltl_to_automata_preprocess, propositional},
+    order::apply_order_options,
+    trace::Trace,
+    util::pick_state,
+    verdict::Verdict,
+    Bdd, BddManager,
+};
+use fsmbdd::FsmBdd;
+use smv::{bdd::SmvBdd, Expr, Infix, Prefix, Smv};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+use sylvan::lace_run;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ctl {
+    /// A propositional formula over the model, possibly with defines and comparisons of
+    /// enumerations and words.
+    Atom(Expr),
+    Constant(bool),
+    Not(Box<Ctl>),
+    And(Box<Ctl>, Box<Ctl>),
+    Or(Box<Ctl>, Box<Ctl>),
+    Imply(Box<Ctl>, Box<Ctl>),
+    Iff(Box<Ctl>, Box<Ctl>),
+    EX(Box<Ctl>),
+    AX(Box<Ctl>),
+    EF(Box<Ctl>),
+    AF(Box<Ctl>),
+    EG(Box<Ctl>),
+    AG(Box<Ctl>),
+    EU(Box<Ctl>, Box<Ctl>),
+    AU(Box<Ctl>, Box<Ctl>),
+}
+
+impl Display for Ctl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ctl::Atom(expr) => write!(f, "{}", expr),
+            Ctl::Constant(value) => write!(f, "{}", if *value { "TRUE" } else { "FALSE" }),
+            Ctl::Not(a) => write!(f, "!({})", a),
+            Ctl::And(a, b) => write!(f, "({} & {})", a, b),
+            Ctl::Or(a, b) => write!(f, "({} | {})", a, b),
+            Ctl::Imply(a, b) => write!(f, "({} -> {})", a, b),
+            Ctl::Iff(a, b) => write!(f, "({} <-> {})", a, b),
+            Ctl::EX(a) => write!(f, "EX {}", a),
+            Ctl::AX(a) => write!(f, "AX {}", a),
+            Ctl::EF(a) => write!(f, "EF {}", a),
+            Ctl::AF(a) => write!(f, "AF {}", a),
+            Ctl::EG(a) => write!(f, "EG {}", a),
+            Ctl::AG(a) => write!(f, "AG {}", a),
+            Ctl::EU(a, b) => write!(f, "E [{} U {}]", a, b),
+            Ctl::AU(a, b) => write!(f, "A [{} U {}]", a, b),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '$' | '#'))
+            {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            let rest: String = chars[i..].iter().take(3).collect();
+            let op = ["<->", "->", "!=", "<=", ">="]
+                .into_iter()
+                .find(|op| rest.starts_with(op))
+                .unwrap_or(&rest[..c.len_utf8()]);
+            tokens.push(op.to_string());
+            i += op.chars().count();
+        }
+    }
+    tokens
+}
+
+/// Operators allowed inside an atom, between identifiers and values.
+const ATOM_OPERATORS: [&str; 8] = ["=", "!=", "<", ">", "<=", ">=", "+", "-"];
+
+fn is_operand(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+}
+
+struct CtlParser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl CtlParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of ctl formula".to_string())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!("expected '{}' but found '{}'", expected, token));
+        }
+        Ok(())
+    }
+
+    fn parse_iff(&mut self) -> Result<Ctl, String> {
+        let left = self.parse_imply()?;
+        if self.peek() == Some("<->") {
+            self.pos += 1;
+            let right = self.parse_iff()?;
+            return Ok(Ctl::Iff(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_imply(&mut self) -> Result<Ctl, String> {
+        let left = self.parse_or()?;
+        if self.peek() == Some("->") {
+            self.pos += 1;
+            let right = self.parse_imply()?;
+            return Ok(Ctl::Imply(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Ctl, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some("|") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Ctl::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Ctl, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some("&") {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Ctl::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_until(&mut self) -> Result<(Box<Ctl>, Box<Ctl>), String> {
+        self.expect("[")?;
+        let left = self.parse_iff()?;
+        self.expect("U")?;
+        let right = self.parse_iff()?;
+        self.expect("]")?;
+        Ok((Box::new(left), Box::new(right)))
+    }
+
+    /// Parses a comparison or arithmetic over identifiers and values, starting at its
+    /// first operand, through the smv expression parser.
+    fn parse_atom(&mut self, first: String) -> Result<Expr, String> {
+        let mut atom = vec![first];
+        while let Some(op) = self.peek().filter(|token| ATOM_OPERATORS.contains(token)) {
+            atom.push(op.to_string());
+            self.pos += 1;
+            if self.peek() == Some("-") {
+                atom.push(self.next()?);
+            }
+            let operand = self.next()?;
+            if !is_operand(&operand) {
+                return Err(format!("unexpected '{}' in ctl atom", operand));
+            }
+            atom.push(operand);
+        }
+        Ok(Expr::from(atom.join(" ").as_str()))
+    }
+
+    fn parse_unary(&mut self) -> Result<Ctl, String> {
+        let token = self.next()?;
+        let unary = |parser: &mut Self| parser.parse_unary().map(Box::new);
+        Ok(match token.as_str() {
+            "(" => {
+                let ctl = self.parse_iff()?;
+                self.expect(")")?;
+                ctl
+            }
+            "!" => Ctl::Not(unary(self)?),
+            "EX" => Ctl::EX(unary(self)?),
+            "AX" => Ctl::AX(unary(self)?),
+            "EF" => Ctl::EF(unary(self)?),
+            "AF" => Ctl::AF(unary(self)?),
+            "EG" => Ctl::EG(unary(self)?),
+            "AG" => Ctl::AG(unary(self)?),
+            "E" if self.peek() == Some("[") => {
+                let (left, right) = self.parse_until()?;
+                Ctl::EU(left, right)
+            }
+            "A" if self.peek() == Some("[") => {
+                let (left, right) = self.parse_until()?;
+                Ctl::AU(left, right)
+            }
+            "TRUE" => Ctl::Constant(true),
+            "FALSE" => Ctl::Constant(false),
+            _ if is_operand(&token) => Ctl::Atom(self.parse_atom(token)?),
+            _ => return Err(format!("unexpected '{}' in ctl formula", token)),
+        })
+    }
+}
+
+impl TryFrom<&str> for Ctl {
+    type Error = String;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let mut parser = CtlParser {
+            tokens: tokenize(input),
+            pos: 0,
+        };
+        let ctl = parser.parse_iff()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected '{}' after ctl formula", token));
+        }
+        Ok(ctl)
+    }
+}
+
+impl TryFrom<&Expr> for Ctl {
+    type Error = String;
+
+    /// Converts a `SPEC` or `CTLSPEC` of the model, keeping its subformulas without ctl
+    /// operators as atoms.
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        let ctl = |expr: &Expr| Ctl::try_from(expr).map(Box::new);
+        Ok(match expr {
+            Expr::LitExpr(value) => Ctl::Constant(*value),
+            Expr::PrefixExpr(prefix, arg) => match prefix {
+                Prefix::Not => Ctl::Not(ctl(arg)?),
+                Prefix::CtlEX => Ctl::EX(ctl(arg)?),
+                Prefix::CtlAX => Ctl::AX(ctl(arg)?),
+                Prefix::CtlEF => Ctl::EF(ctl(arg)?),
+                Prefix::CtlAF => Ctl::AF(ctl(arg)?),
+                Prefix::CtlEG => Ctl::EG(ctl(arg)?),
+                Prefix::CtlAG => Ctl::AG(ctl(arg)?),
+                _ => Ctl::Atom(expr.clone()),
+            },
+            Expr::InfixExpr(infix, left, right) => match infix {
+                Infix::And => Ctl::And(ctl(left)?, ctl(right)?),
+                Infix::Or => Ctl::Or(ctl(left)?, ctl(right)?),
+                Infix::Imply => Ctl::Imply(ctl(left)?, ctl(right)?),
+                Infix::Iff => Ctl::Iff(ctl(left)?, ctl(right)?),
+                Infix::CtlEU => Ctl::EU(ctl(left)?, ctl(right)?),
+                Infix::CtlAU => Ctl::AU(ctl(left)?, ctl(right)?),
+                _ => Ctl::Atom(expr.clone()),
+            },
+            _ => Ctl::Atom(expr.clone()),
+        })
+    }
+}
+
+pub struct CtlChecker<'a> {
+    fsmbdd: &'a FsmBdd<BddManager>,
+    symbols: HashMap<String, Bdd>,
+    /// BDDs of the resolved atoms, by their text
+    atoms: HashMap<String, Bdd>,
+    fair: Bdd,
+}
+
+impl<'a> CtlChecker<'a> {
+    pub fn new(fsmbdd: &'a FsmBdd<BddManager>, defines: &HashMap<String, Bdd>) -> Self {
+        let mut symbols = defines.clone();
+        for (ident, id) in fsmbdd.symbols.iter() {
+            symbols.insert(ident.clone(), fsmbdd.manager.ith_var(*id));
+        }
+        let mut checker = Self {
+            fsmbdd,
+            symbols,
+            atoms: HashMap::new(),
+            fair: fsmbdd.manager.constant(true),
+        };
+        checker.fair = checker.fair_eg(&fsmbdd.manager.constant(true));
+        checker
+    }
+
+    /// Resolves the atoms of `ctl` into BDDs, flattening the defines, case expressions and
+    /// comparisons of the model.
+    pub fn resolve(&mut self, smv: &Smv, ctl: &Ctl) -> Result<(), String> {
+        match ctl {
+            Ctl::Atom(expr) => {
+                let flat = ltl_to_automata_preprocess(smv, expr.clone());
+                let bdd = propositional(&flat, &self.symbols, &self.fsmbdd.manager)
+                    .ok_or_else(|| format!("'{}' is not a propositional formula", expr))?;
+                self.atoms.insert(expr.to_string(), bdd);
+            }
+            Ctl::Constant(_) => (),
+            Ctl::Not(a)
+            | Ctl::EX(a)
+            | Ctl::AX(a)
+            | Ctl::EF(a)
+            | Ctl::AF(a)
+            | Ctl::EG(a)
+            | Ctl::AG(a) => self.resolve(smv, a)?,
+            Ctl::And(a, b)
+            | Ctl::Or(a, b)
+            | Ctl::Imply(a, b)
+            | Ctl::Iff(a, b)
+            | Ctl::EU(a, b)
+            | Ctl::AU(a, b) => {
+                self.resolve(smv, a)?;
+                self.resolve(smv, b)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn constant(&self, value: bool) -> Bdd {
+        self.fsmbdd.manager.constant(value)
+    }
+
+    fn eu(&self, f: &Bdd, g: &Bdd) -> Bdd {
+        let mut reach = g.clone();
+        loop {
+            let new = self.fsmbdd.pre_image(&reach) & f | &reach;
+            if new == reach {
+                return reach;
+            }
+            reach = new;
+        }
+    }
+
+    /// Emerson-Lei fixpoint for `EG f` under the justice constraints.
+    fn fair_eg(&self, f: &Bdd) -> Bdd {
+        let mut z = f.clone();
+        loop {
+            let mut new = z.clone();
+            if self.fsmbdd.justice.is_empty() {
+                new &= self.fsmbdd.pre_image(&z);
+            }
+            for justice in self.fsmbdd.justice.iter() {
+                new &= self.fsmbdd.pre_image(&self.eu(f, &(&z & justice)));
+            }
+            if new == z {
+                return z;
+            }
+            z = new;
+        }
+    }
+
+    pub fn sat(&self, ctl: &Ctl) -> Bdd {
+        match ctl {
+            Ctl::Atom(expr) => self.atoms[&expr.to_string()].clone(),
+            Ctl::Constant(value) => self.constant(*value),
+            Ctl::Not(a) => !self.sat(a),
+            Ctl::And(a, b) => self.sat(a) & self.sat(b),
+            Ctl::Or(a, b) => self.sat(a) | self.sat(b),
+            Ctl::Imply(a, b) => !self.sat(a) | self.sat(b),
+            Ctl::Iff(a, b) => {
+                let a = self.sat(a);
+                let b = self.sat(b);
+                (&a & &b) | (!a & !b)
+            }
+            Ctl::EX(a) => self.fsmbdd.pre_image(&(self.sat(a) & &self.fair)),
+            Ctl::AX(a) => !self.sat(&Ctl::EX(Box::new(Ctl::Not(a.clone())))),
+            Ctl::EF(a) => self.eu(&self.constant(true), &(self.sat(a) & &self.fair)),
+            Ctl::AF(a) => !self.fair_eg(&!self.sat(a)),
+            Ctl::EG(a) => self.fair_eg(&self.sat(a)),
+            Ctl::AG(a) => !self.sat(&Ctl::EF(Box::new(Ctl::Not(a.clone())))),
+            Ctl::EU(a, b) => self.eu(&self.sat(a), &(self.sat(b) & &self.fair)),
+            Ctl::AU(a, b) => {
+                let a = self.sat(a);
+                let b = self.sat(b);
+                let eu = self.eu(&!&b, &(!a & !&b & &self.fair));
+                !(eu | self.fair_eg(&!b))
+            }
+        }
+    }
+
+    /// Fair initial states violating `ctl`.
+    fn violating(&self, ctl: &Ctl) -> Bdd {
+        &self.fsmbdd.init & &self.fair & !self.sat(ctl)
+    }
+
+    /// States following `from` inside `within` that lead to `target`, ending in a target
+    /// state. With `strict` at least one step is taken.
+    fn path_to(&self, from: &Bdd, within: &Bdd, target: &Bdd, strict: bool) -> Option<Vec<Bdd>> {
+        let mut rings = vec![target & within];
+        loop {
+            let last = rings.last().unwrap();
+            let new = self.fsmbdd.pre_image(last) & within & !last;
+            if new.is_constant(false) {
+                break;
+            }
+            let ring = new | last;
+            rings.push(ring);
+        }
+        let mut path = Vec::new();
+        let mut state = from.clone();
+        if !strict && !(&state & &rings[0]).is_constant(false) {
+            return Some(path);
+        }
+        let mut ring = rings.len();
+        loop {
+            let image = self.fsmbdd.post_image(&state);
+            let reach = (0..ring).find(|i| !(&image & &rings[*i]).is_constant(false))?;
+            let next = image & &rings[reach];
+            state = pick_state(&self.fsmbdd.symbols, &self.fsmbdd.manager, &next);
+            path.push(state.clone());
+            if reach == 0 {
+                return Some(path);
+            }
+            ring = reach;
+        }
+    }
+
+    fn eu_witness(&self, state: Bdd, f: &Bdd, g: &Bdd) -> Option<Vec<Bdd>> {
+        let target = g & &self.fair;
+        let mut path = vec![state.clone()];
+        path.extend(self.path_to(&state, &(f | &target), &target, false)?);
+        Some(path)
+    }
+
+    fn eg_witness(&self, state: Bdd, f: &Bdd) -> Option<(Vec<Bdd>, usize)> {
+        let within = self.fair_eg(f);
+        let justice = if self.fsmbdd.justice.is_empty() {
+            vec![self.constant(true)]
+        } else {
+            self.fsmbdd.justice.clone()
+        };
+        let mut path = vec![state];
+        loop {
+            let anchor = path.len() - 1;
+            for justice in justice.iter() {
+                let from = path[path.len() - 1].clone();
+                path.extend(self.path_to(&from, &within, &(&within & justice), false)?);
+            }
+            let from = path[path.len() - 1].clone();
+            if let Some(mut close) = self.path_to(&from, &within, &path[anchor], true) {
+                close.pop();
+                path.extend(close);
+                return Some((path, anchor));
+            }
+        }
+    }
+
+    /// Witness of the negation of `ctl` from a violating initial state, when the negation
+    /// starts with an existential until, globally or next operator.
+    fn witness(&self, ctl: &Ctl, state: Bdd) -> Option<(Vec<Bdd>, Option<usize>)> {
+        Some(match ctl {
+            Ctl::AG(a) => (
+                self.eu_witness(state, &self.constant(true), &!self.sat(a))?,
+                None,
+            ),
+            Ctl::AU(a, b) => {
+                let a = self.sat(a);
+                let b = self.sat(b);
+                let eg = self.fair_eg(&!&b);
+                if !(&state & &eg).is_constant(false) {
+                    let (path, anchor) = self.eg_witness(state, &!b)?;
+                    (path, Some(anchor))
+                } else {
+                    (self.eu_witness(state, &!&b, &(!a & !b))?, None)
+                }
+            }
+            Ctl::AF(a) => {
+                let (path, anchor) = self.eg_witness(state, &!self.sat(a))?;
+                (path, Some(anchor))
+            }
+            Ctl::AX(a) => {
+                let target = !self.sat(a) & &self.fair;
+                let next = self.path_to(&state, &target, &target, true)?;
+                (vec![state, next[0].clone()], None)
+            }
+            _ => (vec![state], None),
+        })
+    }
+
+    /// Checks `ctl`, with a counterexample if it is violated.
+    pub fn check(&self, ctl: &Ctl) -> Verdict {
+        let violating = self.violating(ctl);
+        if violating.is_constant(false) {
+            return Verdict::Holds;
+        }
+        let state = pick_state(&self.fsmbdd.symbols, &self.fsmbdd.manager, &violating);
+        let Some((path, loop_start)) = self.witness(ctl, state) else {
+            return Verdict::Unknown("no witness for the violating initial state".to_string());
+        };
+        let mut symbols: Vec<(&String, &usize)> = self.fsmbdd.symbols.iter().collect();
+        symbols.sort();
+        let states = path
+            .iter()
+            .map(|state| {
+                symbols
+                    .iter()
+                    .map(|(name, id)| {
+                        let var = self.fsmbdd.manager.ith_var(**id);
+                        ((*name).clone(), !(state & var).is_constant(false))
+                    })
+                    .collect()
+            })
+            .collect();
+        Verdict::Violated(Some(Trace {
+            states,
+            loop_start,
+            ..Default::default()
+        }))
+    }
+}
+
+pub fn check(manager: BddManager, smv: Smv, args: Args) -> (Verdict, Duration) {
+    let smv_bdd = SmvBdd::new(&manager, &smv);
+    let fsmbdd = smv_bdd.to_fsmbdd(args.trans_method.into());
+    let (fsmbdd, defines) = match apply_order_options(&args, fsmbdd, smv_bdd.defines.clone()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    let mut specs: Vec<(String, Result<Ctl, String>)> = smv
+        .ctlspecs
+        .iter()
+        .map(|spec| (spec.to_string(), Ctl::try_from(spec)))
+        .collect();
+    specs.extend(
+        args.ctl
+            .iter()
+            .map(|spec| (spec.clone(), Ctl::try_from(spec.as_str()))),
+    );
+    eprintln!("ctl model checking begin");
+    let start = Instant::now();
+    let domains = enum_domains(&smv);
+    let check = || {
+        let mut checker = CtlChecker::new(&fsmbdd, &defines);
+        let mut verdicts = Vec::new();
+        for (text, spec) in specs.iter() {
+            let spec = spec
+                .clone()
+                .and_then(|spec| checker.resolve(&smv, &spec).map(|_| spec));
+            let mut verdict = match spec {
+                Err(error) => Verdict::Unknown(error),
+                Ok(spec) => checker.check(&spec),
+            };
+            if let Verdict::Violated(Some(trace)) = &mut verdict {
+                trace.domains = domains.clone();
+            }
+            print!("{}", verdict.nusmv(text));
+            verdicts.push(verdict);
+        }
+        verdicts
+    };
+    let verdicts = if args.parallel_mode != ParallelMode::Lace {
+        check()
+    } else {
+        lace_run(|_| check())
+    };
+    // violated if any specification is, otherwise unknown if any specification is
+    let verdict = verdicts
+        .iter()
+        .find(|verdict| matches!(verdict, Verdict::Violated(_)))
+        .or_else(|| {
+            verdicts
+                .iter()
+                .find(|verdict| matches!(verdict, Verdict::Unknown(_)))
+        })
+        .cloned()
+        .unwrap_or(Verdict::Holds);
+    (verdict, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ctl;
+    use smv::Expr;
+
+    #[test]
+    fn parse_atoms() {
+        let atom = |expr: &str| Box::new(Ctl::Atom(Expr::from(expr)));
+        assert_eq!(Ctl::try_from("AG (x = 1)"), Ok(Ctl::AG(atom("x = 1"))));
+        assert_eq!(
+            Ctl::try_from("EF state = busy"),
+            Ok(Ctl::EF(atom("state = busy")))
+        );
+        assert_eq!(
+            Ctl::try_from("E [a U b != c & d]"),
+            Ok(Ctl::EU(
+                atom("a"),
+                Box::new(Ctl::And(atom("b != c"), atom("d")))
+            ))
+        );
+        assert!(Ctl::try_from("AG (x = )").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    automata::BuchiAutomata,
+    command::Args,
+    formula::{to_formula, FormulaPrinter},
+    order::support,
+};
+use std::{collections::HashMap, fmt::Display, fs, io};
+
+/// An automaton with state-based Büchi acceptance and labels over the indices of its
+/// atomic propositions, written in HOA v1.
+pub struct Hoa {
+    /// Extra header lines, with their values quoted.
+    pub headers: Vec<(&'static str, String)>,
+    pub init_states: Vec<usize>,
+    pub aps: Vec<String>,
+    pub accepting_states: Vec<usize>,
+    /// The `(label, next)` edges leaving every state.
+    pub edges: Vec<Vec<(String, usize)>>,
+}
+
+impl Display for Hoa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "HOA: v1")?;
+        for (key, value) in self.headers.iter() {
+            writeln!(f, "{}: {:?}", key, value)?;
+        }
+        writeln!(f, "States: {}", self.edges.len())?;
+        for init in self.init_states.iter() {
+            writeln!(f, "Start: {}", init)?;
+        }
+        write!(f, "AP: {}", self.aps.len())?;
+        for ap in self.aps.iter() {
+            write!(f, " {:?}", ap)?;
+        }
+        writeln!(f, "\nacc-name: Buchi\nAcceptance: 1 Inf(0)")?;
+        writeln!(f, "properties: trans-labels explicit-labels state-acc")?;
+        writeln!(f, "--BODY--")?;
+        for (state, edges) in self.edges.iter().enumerate() {
+            if self.accepting_states.contains(&state) {
+                writeln!(f, "State: {} {{0}}", state)?;
+            } else {
+                writeln!(f, "State: {}", state)?;
+            }
+            for (label, next) in edges.iter() {
+                writeln!(f, "[{}] {}", label, next)?;
+            }
+        }
+        writeln!(f, "--END--")
+    }
+}
+
+impl BuchiAutomata {
+    fn var_names(&self) -> HashMap<usize, String> {
+        self.symbols
+            .iter()
+            .map(|(name, id)| (*id, name.clone()))
+            .collect()
+    }
+
+    /// Graphviz rendering, with initial states drawn bold and accepting states doubly
+    /// circled.
+    pub fn to_dot(&self) -> String {
+        let printer = FormulaPrinter::new(&self.manager, &self.symbols);
+        let mut dot = String::from("digraph automaton {\n    rankdir=LR;\n");
+        for state in 0..self.num_state() {
+            let shape = if self.accepting_states.contains(&state) {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let style = if self.init_states.contains(&state) {
+                ", style=bold"
+            } else {
+                ""
+            };
+            dot.push_str(&format!("    {} [shape={}{}];\n", state, shape, style));
+        }
+        for state in 0..self.num_state() {
+            for (next, label) in self.forward[state].iter() {
+                let label = printer.print(label).replace('"', "\\\"");
+                dot.push_str(&format!("    {} -> {} [label=\"{}\"];\n", state, next, label));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// HOA v1 rendering with state-based Büchi acceptance.
+    pub fn to_hoa(&self) -> String {
+        let mut names = self.var_names();
+        let mut vars: Vec<usize> = self
+            .forward
+            .iter()
+            .flatten()
+            .flat_map(|(_, label)| support(label))
+            .collect();
+        vars.sort();
+        vars.dedup();
+        let ap_index: HashMap<usize, usize> =
+            vars.iter().enumerate().map(|(i, var)| (*var, i)).collect();
+        let edges = self
+            .forward
+            .iter()
+            .map(|edges| {
+                edges
+                    .iter()
+                    .map(|(next, label)| {
+                        let label = if label.is_constant(true) {
+                            "t".to_string()
+                        } else if label.is_constant(false) {
+                            "f".to_string()
+                        } else {
+                            to_formula(&self.manager, label, |var| ap_index[&var].to_string())
+                        };
+                        (label, *next)
+                    })
+                    .collect()
+            })
+            .collect();
+        Hoa {
+            headers: Vec::new(),
+            init_states: self.init_states.clone(),
+            aps: vars
+                .iter()
+                .map(|var| names.remove(var).unwrap_or_else(|| format!("v{}", var)))
+                .collect(),
+            accepting_states: self.accepting_states.clone(),
+            edges,
+        }
+        .to_string()
+    }
+
+    /// Writes the automaton to `path`, in HOA if it ends with `.hoa` and in DOT otherwise.
+    pub fn dump(&self, path: &str) -> io::Result<()> {
+        let content = if path.ends_with(".hoa") {
+            self.to_hoa()
+        } else {
+            self.to_dot()
+        };
+        fs::write(path, content)
+    }
+}
+
+/// Dumps the automaton if asked on the command line.
+pub fn dump_automaton(args: &Args, automata: &BuchiAutomata) {
+    if let Some(path) = &args.dump_automaton {
+        automata.dump(path).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{Bdd, BddManager};
+use smv::{Smv, VarType};
+use std::collections::{HashMap, HashSet};
+
+type Cube = Vec<(usize, bool)>;
+
+/// Irredundant sum of products of `bdd` with the Minato-Morreale algorithm, as cubes of
+/// `(var, value)` literals.
+pub fn isop(manager: &BddManager, bdd: &Bdd) -> Vec<Cube> {
+    fn isop_rec(
+        manager: &BddManager,
+        lower: &Bdd,
+        upper: &Bdd,
+        cache: &mut HashMap<(Bdd, Bdd), (Bdd, Vec<Cube>)>,
+    ) -> (Bdd, Vec<Cube>) {
+        if lower.is_constant(false) {
+            return (manager.constant(false), Vec::new());
+        }
+        if upper.is_constant(true) {
+            return (manager.constant(true), vec![Vec::new()]);
+        }
+        let key = (lower.clone(), upper.clone());
+        if let Some(res) = cache.get(&key) {
+            return res.clone();
+        }
+        let var = if lower.is_constant(true) {
+            upper.var()
+        } else {
+            lower.var()
+        };
+        let x = manager.ith_var(var);
+        let (lower0, lower1) = (lower.restrict(&!&x), lower.restrict(&x));
+        let (upper0, upper1) = (upper.restrict(&!&x), upper.restrict(&x));
+        let (bdd0, cubes0) = isop_rec(manager, &(&lower0 & !&upper1), &upper0, cache);
+        let (bdd1, cubes1) = isop_rec(manager, &(&lower1 & !&upper0), &upper1, cache);
+        let lower_star = (lower0 & !&bdd0) | (lower1 & !&bdd1);
+        let upper_star = upper0 & upper1;
+        let (bdd_star, cubes_star) = isop_rec(manager, &lower_star, &upper_star, cache);
+        let bdd = (!&x & bdd0) | (&x & bdd1) | bdd_star;
+        let mut cubes = Vec::new();
+        for (mut cube, value) in cubes0
+            .into_iter()
+            .map(|cube| (cube, false))
+            .chain(cubes1.into_iter().map(|cube| (cube, true)))
+        {
+            cube.insert(0, (var, value));
+            cubes.push(cube);
+        }
+        cubes.extend(cubes_star);
+        cache.insert(key, (bdd.clone(), cubes.clone()));
+        (bdd, cubes)
+    }
+    isop_rec(manager, bdd, bdd, &mut HashMap::new()).1
+}
+
+fn join_cubes(cubes: Vec<Vec<String>>) -> String {
+    let multiple = cubes.len() > 1;
+    cubes
+        .into_iter()
+        .map(|literals| {
+            if multiple && literals.len() > 1 {
+                format!("({})", literals.join(" & "))
+            } else {
+                literals.join(" & ")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Renders `bdd` as an irredundant sum of cubes, naming its variables with `name`.
+pub fn to_formula(manager: &BddManager, bdd: &Bdd, name: impl Fn(usize) -> String) -> String {
+    if bdd.is_constant(true) {
+        return "TRUE".to_string();
+    }
+    if bdd.is_constant(false) {
+        return "FALSE".to_string();
+    }
+    let cubes = isop(manager, bdd)
+        .into_iter()
+        .map(|cube| {
+            cube.into_iter()
+                .map(|(var, value)| format!("{}{}", if value { "" } else { "!" }, name(var)))
+                .collect()
+        })
+        .collect();
+    join_cubes(cubes)
+}
+
+/// Splits the name of bit `i` of a bitvector, `name[i]`, into its vector and bit index.
+fn split_bit(name: &str) -> Option<(&str, usize)> {
+    let (vector, index) = name.strip_suffix(']')?.rsplit_once('[')?;
+    Some((vector, index.parse().ok()?))
+}
+
+/// Literals of the enumeration variables of the model, indexed by the value of the bits
+/// encoding them.
+pub type Domains = HashMap<String, Vec<String>>;
+
+pub fn enum_domains(smv: &Smv) -> Domains {
+    smv.vars
+        .iter()
+        .filter_map(|var| match &var.ty {
+            VarType::Enum(values) => Some((
+                var.ident.clone(),
+                values.iter().map(|value| value.to_string()).collect(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Renders a vector from the `(index, value)` of its bits, as an enumeration literal when
+/// `domain` names it, in decimal when it fits in 128 bits and as a binary word otherwise.
+fn vector_value(bits: &[(usize, bool)], domain: Option<&Vec<String>>) -> String {
+    let width = bits.iter().map(|(index, _)| index + 1).max().unwrap_or(0);
+    if width > 128 {
+        let mut digits = vec!['0'; width];
+        for (index, _) in bits.iter().filter(|(_, value)| *value) {
+            digits[width - 1 - index] = '1';
+        }
+        return format!("0ub{}_{}", width, digits.into_iter().collect::<String>());
+    }
+    let value = bits
+        .iter()
+        .filter(|(_, value)| *value)
+        .fold(0u128, |value, (index, _)| value | 1 << index);
+    let literal = usize::try_from(value)
+        .ok()
+        .and_then(|value| domain?.get(value));
+    match literal {
+        Some(literal) => literal.clone(),
+        None => value.to_string(),
+    }
+}
+
+/// Values of the bits of a vector in `assignment`, if they are all assigned.
+fn assigned_bits(
+    assignment: &HashMap<usize, bool>,
+    bits: &[(usize, usize)],
+) -> Option<Vec<(usize, bool)>> {
+    bits.iter()
+        .map(|(index, var)| Some((*index, *assignment.get(var)?)))
+        .collect()
+}
+
+/// Renders BDDs over the model symbols, collapsing fully assigned bitvectors of
+/// enumerations and words back to `var = value`.
+pub struct FormulaPrinter {
+    manager: BddManager,
+    names: HashMap<usize, String>,
+    vectors: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl FormulaPrinter {
+    pub fn new(manager: &BddManager, symbols: &HashMap<String, usize>) -> Self {
+        let mut vectors: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for (name, id) in symbols.iter() {
+            if let Some((vector, index)) = split_bit(name) {
+                vectors
+                    .entry(vector.to_string())
+                    .or_default()
+                    .push((index, *id));
+            }
+        }
+        Self {
+            manager: manager.clone(),
+            names: symbols.iter().map(|(name, id)| (*id, name.clone())).collect(),
+            vectors,
+        }
+    }
+
+    fn name(&self, var: usize) -> String {
+        self.names
+            .get(&var)
+            .cloned()
+            .unwrap_or_else(|| format!("v{}", var))
+    }
+
+    fn cube(&self, cube: Cube) -> Vec<String> {
+        let assignment: HashMap<usize, bool> = cube.iter().copied().collect();
+        let mut collapsed = HashSet::new();
+        let mut literals = Vec::new();
+        for (var, value) in cube {
+            let name = self.name(var);
+            if let Some((vector, _)) = split_bit(&name) {
+                if let Some(bits) = assigned_bits(&assignment, &self.vectors[vector]) {
+                    if collapsed.insert(vector.to_string()) {
+                        literals.push(format!("{} = {}", vector, vector_value(&bits, None)));
+                    }
+                    continue;
+                }
+            }
+            literals.push(format!("{}{}", if value { "" } else { "!" }, name));
+        }
+        literals
+    }
+
+    pub fn print(&self, bdd: &Bdd) -> String {
+        if bdd.is_constant(true) {
+            return "TRUE".to_string();
+        }
+        if bdd.is_constant(false) {
+            return "FALSE".to_string();
+        }
+        let cubes = isop(&self.manager, bdd)
+            .into_iter()
+            .map(|cube| self.cube(cube))
+            .collect();
+        join_cubes(cubes)
+    }
+}
+
+/// Collapses the bits `name[i]` of a state assignment back to `name = value`, keeping the
+/// position of the first bit of each vector, with the literals of `domains` as the values
+/// of enumerations.
+pub fn collapse_assignment(
+    assignment: &[(String, bool)],
+    domains: &Domains,
+) -> Vec<(String, String)> {
+    let mut vectors: HashMap<&str, Vec<(usize, bool)>> = HashMap::new();
+    for (name, value) in assignment.iter() {
+        if let Some((vector, index)) = split_bit(name) {
+            vectors.entry(vector).or_default().push((index, *value));
+        }
+    }
+    let mut res = Vec::new();
+    for (name, value) in assignment.iter() {
+        match split_bit(name) {
+            Some((vector, _)) => {
+                if let Some(bits) = vectors.remove(vector) {
+                    let value = vector_value(&bits, domains.get(vector));
+                    res.push((vector.to_string(), value));
+                }
+            }
+            None => {
+                let value = if *value { "TRUE" } else { "FALSE" };
+                res.push((name.clone(), value.to_string()));
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(name: &str, value: u128, width: usize) -> Vec<(String, bool)> {
+        (0..width)
+            .map(|i| (format!("{}[{}]", name, i), i < 128 && (value >> i) & 1 == 1))
+            .collect()
+    }
+
+    #[test]
+    fn collapse() {
+        let mut assignment = vec![("b".to_string(), true)];
+        assignment.extend(bits("state", 2, 2));
+        assignment.extend(bits("w", 5, 4));
+        let literals = ["idle", "busy", "done"].map(String::from).to_vec();
+        let domains = Domains::from([("state".to_string(), literals)]);
+        let state = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            collapse_assignment(&assignment, &domains),
+            vec![state("b", "TRUE"), state("state", "done"), state("w", "5")]
+        );
+    }
+
+    #[test]
+    fn collapse_wide_vector() {
+        let mut assignment = bits("w", 1, 130);
+        assignment[129].1 = true;
+        let state = collapse_assignment(&assignment, &Domains::new());
+        assert_eq!(state[0].1, format!("0ub130_1{}1", "0".repeat(128)));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    command::{Args, ParallelMode},
+    export::dump_automaton,
+    ltl::ltl_automata,
+    order::apply_order_options,
+    partitioned::get_ltl,
+    verdict::Verdict,
+    Bdd, BddManager,
+};
+use fsmbdd::FsmBdd;
+use smv::{bdd::SmvBdd, Smv};
+use std::time::{Duration, Instant};
+use sylvan::lace_run;
+
+/// Liveness-to-safety reduction by state copying. A nondeterministically saved copy of
+/// the current state is kept in shadow variables, and the stages record which justice
+/// constraints have been visited since the copy was saved. A fair lasso exists iff a
+/// state of the last stage equals its saved copy.
+struct LivenessToSafety<'a> {
+    fsmbdd: &'a FsmBdd<BddManager>,
+    justice: Vec<Bdd>,
+    equal: Bdd,
+    verbose: bool,
+}
+
+impl<'a> LivenessToSafety<'a> {
+    fn new(fsmbdd: &'a FsmBdd<BddManager>, verbose: bool) -> Self {
+        let manager = &fsmbdd.manager;
+        let base = fsmbdd.symbols.len();
+        let mut equal = manager.constant(true);
+        let mut vars: Vec<usize> = fsmbdd.symbols.values().copied().collect();
+        vars.sort();
+        for (i, var) in vars.into_iter().enumerate() {
+            let x = manager.ith_var(var);
+            let y = manager.ith_var((base + i) * 2);
+            equal &= (&x & &y) | (!x & !y);
+        }
+        let mut justice = fsmbdd.justice.clone();
+        if justice.is_empty() {
+            justice.push(manager.constant(true));
+        }
+        Self {
+            fsmbdd,
+            justice,
+            equal,
+            verbose,
+        }
+    }
+
+    /// The shadow variables are not in the support of the transition relation, so images
+    /// keep the saved copy unchanged.
+    fn check(&self) -> bool {
+        let manager = &self.fsmbdd.manager;
+        let last = self.justice.len() + 1;
+        let mut reach = vec![manager.constant(false); last + 1];
+        reach[0] = self.fsmbdd.init.clone();
+        let mut frontier = reach.clone();
+        let mut deep = 0;
+        loop {
+            deep += 1;
+            if self.verbose {
+                dbg!(deep);
+            }
+            let mut image: Vec<Bdd> = frontier
+                .iter()
+                .map(|bdd| self.fsmbdd.post_image(bdd))
+                .collect();
+            image[1] |= &frontier[0] & &self.equal;
+            for (j, justice) in self.justice.iter().enumerate() {
+                image[j + 2] |= self.fsmbdd.post_image(&(&frontier[j + 1] & justice));
+            }
+            for i in 0..=last {
+                frontier[i] = &image[i] & !&reach[i];
+                reach[i] |= &frontier[i];
+            }
+            if !(&reach[last] & &self.equal).is_constant(false) {
+                return false;
+            }
+            if frontier.iter().all(|bdd| bdd.is_constant(false)) {
+                return true;
+            }
+        }
+    }
+}
+
+pub fn check(manager: BddManager, smv: Smv, args: Args) -> (Verdict, Duration) {
+    let smv_bdd = SmvBdd::new(&manager, &smv);
+    let fsmbdd = smv_bdd.to_fsmbdd(args.trans_method.into());
+    let (mut fsmbdd, defines) = match apply_order_options(&args, fsmbdd, smv_bdd.defines.clone()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    fsmbdd.justice.clear();
+    let ba = get_ltl(&smv, args.property.index, &args.ltl_extend_trans)
+        .and_then(|ltl| ltl_automata(&smv, &ltl, &mut fsmbdd, &defines, &args));
+    let ba = match ba {
+        Ok(ba) => ba,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    dump_automaton(&args, &ba);
+    let product = fsmbdd.product(&ba.to_fsmbdd());
+    eprintln!("liveness to safety begin");
+    let start = Instant::now();
+    let l2s = LivenessToSafety::new(&product, args.verbose);
+    let res = if args.parallel_mode != ParallelMode::Lace {
+        l2s.check()
+    } else {
+        lace_run(|_| l2s.check())
+    };
+    (Verdict::from_holds(res), start.elapsed())
+}
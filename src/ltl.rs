@@ -1,8 +1,396 @@
-use crate::util::trans_expr_to_ltl;
-use smv::{Expr, Smv};
+use crate::{
+    automata::BuchiAutomata, command::Args, rewrite::rewrite_ltl, util::trans_expr_to_ltl, Bdd,
+    BddManager,
+};
+use fsmbdd::{FsmBdd, Trans, TransBddMethod};
+use smv::{Expr, Infix, Prefix, Smv};
+use std::collections::HashMap;
 
 pub fn ltl_to_automata_preprocess(smv: &Smv, ltl: Expr) -> Expr {
     let ltl = smv.flatten_to_propositional_define(&ltl);
     let ltl = smv.flatten_case(ltl);
     trans_expr_to_ltl(&ltl)
 }
+
+/// The LTL specification `index` of the model.
+pub fn ltl_spec(smv: &Smv, index: usize) -> Result<Expr, String> {
+    smv.ltlspecs
+        .get(index)
+        .cloned()
+        .ok_or_else(|| format!("the model has no ltl specification {}", index))
+}
+
+/// BDD of `expr` over `vars`, if it is a propositional formula.
+pub fn propositional(
+    expr: &Expr,
+    vars: &HashMap<String, Bdd>,
+    manager: &BddManager,
+) -> Option<Bdd> {
+    match expr {
+        Expr::Ident(ident) => vars.get(ident).cloned(),
+        Expr::LitExpr(value) => Some(manager.constant(*value)),
+        Expr::PrefixExpr(Prefix::Not, expr) => Some(!propositional(expr, vars, manager)?),
+        Expr::InfixExpr(infix, left, right) => {
+            let left = propositional(left, vars, manager)?;
+            let right = propositional(right, vars, manager)?;
+            match infix {
+                Infix::And => Some(left & right),
+                Infix::Or => Some(left | right),
+                Infix::Imply => Some(!left | right),
+                Infix::Iff => Some((&left & &right) | (!left & !right)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Replaces the maximal propositional subformulas of an LTL formula by fresh propositions,
+/// so that the translator does not see the inlined DEFINE bodies.
+struct Abstraction<'a> {
+    smv: &'a Smv,
+    manager: BddManager,
+    vars: HashMap<String, Bdd>,
+    propositions: HashMap<Bdd, String>,
+    next_id: usize,
+}
+
+impl Abstraction<'_> {
+    fn proposition(&mut self, bdd: Bdd) -> Expr {
+        if bdd.is_constant(true) || bdd.is_constant(false) {
+            return Expr::LitExpr(bdd.is_constant(true));
+        }
+        if let Some(name) = self.propositions.get(&bdd) {
+            return Expr::Ident(name.clone());
+        }
+        let name = loop {
+            let name = format!("p{}", self.next_id);
+            self.next_id += 1;
+            if !self.vars.contains_key(&name) {
+                break name;
+            }
+        };
+        self.propositions.insert(bdd.clone(), name.clone());
+        self.vars.insert(name.clone(), bdd);
+        Expr::Ident(name)
+    }
+
+    /// Abstracts the propositional subformulas of `expr`, as the BDD of `expr` when it is
+    /// propositional as a whole so that its caller can merge it with its siblings.
+    fn abstract_rec(&mut self, expr: &Expr) -> Result<Bdd, Expr> {
+        if let Some(bdd) = propositional(expr, &self.vars, &self.manager) {
+            return Ok(bdd);
+        }
+        match expr {
+            Expr::PrefixExpr(Prefix::Not, arg) => match self.abstract_rec(arg) {
+                Ok(bdd) => Ok(!bdd),
+                Err(arg) => Err(Expr::PrefixExpr(Prefix::Not, Box::new(arg))),
+            },
+            Expr::PrefixExpr(prefix, arg) => Err(Expr::PrefixExpr(
+                prefix.clone(),
+                Box::new(self.abstract_expr(arg)),
+            )),
+            Expr::InfixExpr(
+                infix @ (Infix::And | Infix::Or | Infix::Imply | Infix::Iff),
+                left,
+                right,
+            ) => match (self.abstract_rec(left), self.abstract_rec(right)) {
+                (Ok(left), Ok(right)) => Ok(match infix {
+                    Infix::And => left & right,
+                    Infix::Or => left | right,
+                    Infix::Imply => !left | right,
+                    _ => (&left & &right) | (!left & !right),
+                }),
+                (left, right) => Err(Expr::InfixExpr(
+                    infix.clone(),
+                    Box::new(self.expr_of(left)),
+                    Box::new(self.expr_of(right)),
+                )),
+            },
+            Expr::InfixExpr(
+                infix @ (Infix::LtlUntil
+                | Infix::LtlReleases
+                | Infix::LtlSince
+                | Infix::LtlTriggered),
+                left,
+                right,
+            ) => Err(Expr::InfixExpr(
+                infix.clone(),
+                Box::new(self.abstract_expr(left)),
+                Box::new(self.abstract_expr(right)),
+            )),
+            _ => {
+                // comparisons of words and enumerations and case expressions, which are
+                // inlined if they still are not propositional once flattened
+                let flat = ltl_to_automata_preprocess(self.smv, expr.clone());
+                propositional(&flat, &self.vars, &self.manager).ok_or(flat)
+            }
+        }
+    }
+
+    fn expr_of(&mut self, abstracted: Result<Bdd, Expr>) -> Expr {
+        match abstracted {
+            Ok(bdd) => self.proposition(bdd),
+            Err(expr) => expr,
+        }
+    }
+
+    /// Replaces the maximal propositional subformulas of `expr` by propositions.
+    fn abstract_expr(&mut self, expr: &Expr) -> Expr {
+        let abstracted = self.abstract_rec(expr);
+        self.expr_of(abstracted)
+    }
+}
+
+/// Abstracts the atomic propositions of `ltl` into fresh propositions `p0`, `p1`, ...,
+/// returning the abstracted formula and the defines extended with their BDDs.
+pub fn abstract_propositions(
+    smv: &Smv,
+    ltl: &Expr,
+    manager: &BddManager,
+    symbols: &HashMap<String, usize>,
+    defines: &HashMap<String, Bdd>,
+) -> (Expr, HashMap<String, Bdd>) {
+    let mut vars = defines.clone();
+    for (name, id) in symbols.iter() {
+        vars.insert(name.clone(), manager.ith_var(*id));
+    }
+    let mut abstraction = Abstraction {
+        smv,
+        manager: manager.clone(),
+        vars,
+        propositions: HashMap::new(),
+        next_id: 0,
+    };
+    let ltl = trans_expr_to_ltl(&abstraction.abstract_expr(ltl));
+    let mut defines = defines.clone();
+    for (bdd, name) in abstraction.propositions {
+        defines.insert(name, bdd);
+    }
+    (ltl, defines)
+}
+
+/// Translates the LTL formula `ltl` over the model into a Büchi automaton. Its atomic
+/// propositions are abstracted, the formula is rewritten, and its past-time operators are
+/// compiled into monitors added to `fsmbdd`.
+pub fn ltl_automata(
+    smv: &Smv,
+    ltl: &Expr,
+    fsmbdd: &mut FsmBdd<BddManager>,
+    defines: &HashMap<String, Bdd>,
+    args: &Args,
+) -> Result<BuchiAutomata, String> {
+    let manager = fsmbdd.manager.clone();
+    let (ltl, defines) = abstract_propositions(smv, ltl, &manager, &fsmbdd.symbols, defines);
+    let ltl = rewrite_ltl(&ltl);
+    let ltl = compile_past_operators(&ltl, fsmbdd, &defines, args.trans_method.into())?;
+    eprintln!("{}", ltl);
+    let cache = args.translation_cache.as_deref();
+    Ok(BuchiAutomata::from_ltl(
+        ltl,
+        &manager,
+        &fsmbdd.symbols,
+        &defines,
+        cache,
+    ))
+}
+
+/// The first future-time operator of `expr`, if any.
+fn future_operator(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::PrefixExpr(prefix, arg) => match prefix {
+            Prefix::LtlNext => Some("X"),
+            Prefix::LtlGlobally => Some("G"),
+            Prefix::LtlFinally => Some("F"),
+            _ => future_operator(arg),
+        },
+        Expr::InfixExpr(infix, left, right) => match infix {
+            Infix::LtlUntil => Some("U"),
+            Infix::LtlReleases => Some("V"),
+            _ => future_operator(left).or_else(|| future_operator(right)),
+        },
+        _ => None,
+    }
+}
+
+/// Compiles the past-time operators of an LTL formula into monitor variables of the model,
+/// whose value at every step is the value of the past subformula they replace. Operands
+/// are compiled first, so nested past operators are monitors over the inner monitors.
+struct PastCompiler<'a> {
+    fsmbdd: &'a mut FsmBdd<BddManager>,
+    vars: HashMap<String, Bdd>,
+    trans: Vec<Bdd>,
+}
+
+impl PastCompiler<'_> {
+    /// BDD of the compiled operand `expr` of a past operator, which must not contain
+    /// future-time operators as a monitor can only look backwards.
+    fn propositional(&self, expr: &Expr) -> Result<Bdd, String> {
+        propositional(expr, &self.vars, &self.fsmbdd.manager).ok_or_else(|| {
+            match future_operator(expr) {
+                Some(operator) => format!(
+                    "future operator {} under a past operator is not supported: {}",
+                    operator, expr
+                ),
+                None => format!("not propositional under a past operator: {}", expr),
+            }
+        })
+    }
+
+    /// Adds a monitor variable with initial value `init`, whose next value is given by
+    /// `next` over the monitor itself.
+    fn monitor(&mut self, init: Bdd, next: impl FnOnce(&Bdd) -> Bdd) -> Expr {
+        let manager = self.fsmbdd.manager.clone();
+        let id = self.fsmbdd.symbols.len() * 2;
+        let name = (self.trans.len()..)
+            .map(|i| format!("past{}", i))
+            .find(|name| !self.vars.contains_key(name))
+            .unwrap();
+        let var = manager.ith_var(id);
+        manager.ith_var(id + 1);
+        let next = next(&var);
+        self.fsmbdd.init &= (&var & &init) | (!&var & !init);
+        let next_var = var.next_state();
+        self.trans.push((&next_var & &next) | (!next_var & !next));
+        self.fsmbdd.symbols.insert(name.clone(), id);
+        self.vars.insert(name.clone(), var);
+        Expr::Ident(name)
+    }
+
+    fn compile(&mut self, expr: &Expr) -> Result<Expr, String> {
+        Ok(match expr {
+            Expr::PrefixExpr(prefix, arg) => {
+                let arg = self.compile(arg)?;
+                let past = matches!(
+                    prefix,
+                    Prefix::LtlYesterday
+                        | Prefix::LtlWeakYesterday
+                        | Prefix::LtlOnce
+                        | Prefix::LtlHistorically
+                );
+                if !past {
+                    return Ok(Expr::PrefixExpr(prefix.clone(), Box::new(arg)));
+                }
+                let arg = self.propositional(&arg)?;
+                let manager = self.fsmbdd.manager.clone();
+                match prefix {
+                    Prefix::LtlYesterday => self.monitor(manager.constant(false), |_| arg),
+                    Prefix::LtlWeakYesterday => self.monitor(manager.constant(true), |_| arg),
+                    Prefix::LtlOnce => {
+                        let next = arg.next_state();
+                        self.monitor(arg, |monitor| monitor | &next)
+                    }
+                    Prefix::LtlHistorically => {
+                        let next = arg.next_state();
+                        self.monitor(arg, |monitor| monitor & &next)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Expr::InfixExpr(infix, left, right) => {
+                let left = self.compile(left)?;
+                let right = self.compile(right)?;
+                if !matches!(infix, Infix::LtlSince | Infix::LtlTriggered) {
+                    return Ok(Expr::InfixExpr(
+                        infix.clone(),
+                        Box::new(left),
+                        Box::new(right),
+                    ));
+                }
+                let left = self.propositional(&left)?.next_state();
+                let right = self.propositional(&right)?;
+                let right_next = right.next_state();
+                match infix {
+                    Infix::LtlSince => self.monitor(right, |monitor| right_next | (left & monitor)),
+                    Infix::LtlTriggered => {
+                        self.monitor(right, |monitor| right_next & (left | monitor))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => expr.clone(),
+        })
+    }
+}
+
+/// Replaces the past-time subformulas of `ltl` by monitor variables added to `fsmbdd`, so
+/// that the formula can be translated by a future-time LTL translator.
+pub fn compile_past_operators(
+    ltl: &Expr,
+    fsmbdd: &mut FsmBdd<BddManager>,
+    defines: &HashMap<String, Bdd>,
+    method: TransBddMethod,
+) -> Result<Expr, String> {
+    let mut vars = defines.clone();
+    for (name, id) in fsmbdd.symbols.iter() {
+        vars.insert(name.clone(), fsmbdd.manager.ith_var(*id));
+    }
+    let mut compiler = PastCompiler {
+        fsmbdd,
+        vars,
+        trans: Vec::new(),
+    };
+    let ltl = compiler.compile(ltl)?;
+    let monitors = compiler.trans;
+    if !monitors.is_empty() {
+        let manager = fsmbdd.manager.clone();
+        let mut trans = fsmbdd.trans.trans.clone();
+        trans.extend(monitors);
+        fsmbdd.trans = Trans::new(&manager, trans, method);
+    }
+    Ok(ltl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aiger::Aiger;
+
+    /// A model with the single input `i0`.
+    fn model() -> FsmBdd<BddManager> {
+        let aiger = Aiger::parse(b"aag 1 1 0 0 0\n2\n").unwrap();
+        let manager = crate::test_manager();
+        aiger.to_fsmbdd(&manager, TransBddMethod::Partition).0
+    }
+
+    fn prefix(prefix: Prefix, expr: Expr) -> Expr {
+        Expr::PrefixExpr(prefix, Box::new(expr))
+    }
+
+    fn compile(ltl: &Expr, fsmbdd: &mut FsmBdd<BddManager>) -> Result<Expr, String> {
+        compile_past_operators(ltl, fsmbdd, &HashMap::new(), TransBddMethod::Partition)
+    }
+
+    #[test]
+    fn nested_past() {
+        let mut fsmbdd = model();
+        let i = Expr::Ident("i0".to_string());
+        let once = prefix(Prefix::LtlOnce, i.clone());
+        let since = Expr::InfixExpr(Infix::LtlSince, Box::new(i), Box::new(once));
+        let ltl = prefix(Prefix::LtlGlobally, prefix(Prefix::LtlYesterday, since));
+        let ltl = compile(&ltl, &mut fsmbdd).unwrap();
+        assert_eq!(
+            ltl,
+            prefix(Prefix::LtlGlobally, Expr::Ident("past2".to_string()))
+        );
+        let manager = fsmbdd.manager.clone();
+        let var = |name: &str| manager.ith_var(fsmbdd.symbols[name]);
+        // every monitor is false initially unless its input is: O i0 and i0 S O i0 are
+        // both i0 at the first step, and Y is false
+        let iff = |a: Bdd, b: Bdd| (&a & &b) | (!a & !b);
+        let expected = iff(var("past0"), var("i0")) & iff(var("past1"), var("i0")) & !var("past2");
+        assert!(fsmbdd.init == expected);
+    }
+
+    #[test]
+    fn future_under_past() {
+        let mut fsmbdd = model();
+        let next = prefix(Prefix::LtlNext, Expr::Ident("i0".to_string()));
+        let error = compile(&prefix(Prefix::LtlOnce, next), &mut fsmbdd).unwrap_err();
+        assert!(error.starts_with("future operator X"));
+        let i = Expr::Ident("i0".to_string());
+        let until = Expr::InfixExpr(Infix::LtlUntil, Box::new(i.clone()), Box::new(i));
+        let error = compile(&prefix(Prefix::LtlHistorically, until), &mut fsmbdd).unwrap_err();
+        assert!(error.starts_with("future operator U"));
+    }
+}
@@ -1,18 +1,45 @@
+mod aiger;
 mod automata;
+mod bench;
+mod bmc;
+mod btor2;
+mod budget;
+mod cache;
 mod command;
+mod cross_check;
+mod ctl;
+mod export;
+mod formula;
+mod l2s;
 mod ltl;
+mod order;
 mod partitioned;
+mod property;
+mod rewrite;
+mod sat;
+mod trace;
 mod traditional;
 mod util;
+mod verdict;
 
 use clap::Parser;
-use command::Algorithm;
+use command::{Algorithm, Command, ParallelMode};
 use smv::Smv;
+use std::process;
+use verdict::Verdict;
 
 type BddManager = sylvan::Sylvan;
 type Bdd = sylvan::Bdd;
 
+/// The manager shared by the tests, which may only be initialized once per process.
+#[cfg(test)]
+fn test_manager() -> BddManager {
+    static MANAGER: std::sync::OnceLock<BddManager> = std::sync::OnceLock::new();
+    MANAGER.get_or_init(|| BddManager::init(1)).clone()
+}
+
 fn main() {
+    budget::start();
     // TEST
     // "../MC-Benchmark/LMCS-2006/mutex/mutex-flat.smv";
     // "../MC-Benchmark/LMCS-2006/short/short-flat.smv";
@@ -48,13 +75,56 @@ fn main() {
     // "../MC-Benchmark/hwmcc17/live/arbixs08bugp03-flat.smv").unwrap();
     // "../MC-Benchmark/hwmcc17/single/ringp0-flat.smv";
     let input_file = format!("./benchmark/{}", input_file);
-    let args = command::Args::parse();
-    let smv = Smv::from_file(input_file).unwrap();
+    let mut args = command::Args::parse();
+    if args.close_lace_optimize {
+        args.parallel_mode = ParallelMode::Sequential;
+    }
+    if let Some(Command::Bench(bench_args)) = &args.command {
+        match bench::bench(bench_args) {
+            Ok(consistent) => process::exit(if consistent { 0 } else { 1 }),
+            Err(error) => {
+                eprintln!("bench: {}", error);
+                process::exit(2);
+            }
+        }
+    }
+    let input_file = args.model.clone().unwrap_or(input_file);
     let manager = BddManager::init(args.parallel);
-    let algorithm = match args.algorithm {
-        Algorithm::Partitioned => partitioned::check,
-        Algorithm::Traditional => traditional::check,
+    let property = args.property;
+    let (res, time) = if property::is_hardware_model(&input_file) {
+        property::check(manager, &input_file, args)
+    } else if args.cross_check {
+        match cross_check::check(manager, &input_file, args) {
+            Some(res) => res,
+            None => process::exit(cross_check::DISAGREEMENT_EXIT_CODE),
+        }
+    } else {
+        let smv = match Smv::from_file(input_file.as_str()) {
+            Ok(smv) => smv,
+            Err(error) => {
+                let res = Verdict::Unknown(format!("{}: {:?}", input_file, error));
+                eprintln!("res: {}", res);
+                process::exit(res.exit_code());
+            }
+        };
+        let spec = smv
+            .ltlspecs
+            .get(property.index)
+            .map(|spec| spec.to_string());
+        let algorithm = match args.algorithm {
+            Algorithm::Partitioned => partitioned::check,
+            Algorithm::Traditional => traditional::check,
+            Algorithm::Bmc => bmc::check,
+            Algorithm::LivenessToSafety => l2s::check,
+            Algorithm::Ctl => ctl::check,
+        };
+        let ctl = args.algorithm == Algorithm::Ctl;
+        let (res, time) = algorithm(manager, smv, args);
+        if !ctl {
+            print!("{}", res.nusmv(&spec.unwrap_or_default()));
+        }
+        (res, time)
     };
-    let (res, time) = algorithm(manager, smv, args);
-    println!("res: {}, time: {:?}", res, time);
+    eprintln!("res: {}, time: {:?}", res, time);
+    process::exit(res.exit_code());
 }
@@ -0,0 +1,239 @@
+use crate::{command::Args, Bdd, BddManager};
+use fsmbdd::{FsmBdd, Trans, TransBddMethod};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs, io,
+};
+
+/// Variables in the support of `bdd`, with next state variables mapped to their current
+/// state variables.
+pub fn support(bdd: &Bdd) -> BTreeSet<usize> {
+    fn support_rec(bdd: &Bdd, visited: &mut HashSet<Bdd>, support: &mut BTreeSet<usize>) {
+        if bdd.is_constant(true) || bdd.is_constant(false) || !visited.insert(bdd.clone()) {
+            return;
+        }
+        support.insert(bdd.var() / 2 * 2);
+        support_rec(&bdd.high(), visited, support);
+        support_rec(&bdd.low(), visited, support);
+    }
+    let mut support = BTreeSet::new();
+    support_rec(bdd, &mut HashSet::new(), &mut support);
+    support
+}
+
+/// Renames the variables of `bdd` with `map`, which maps current state variables to
+/// current state variables. Next state variables follow their current state variables.
+pub fn permute(
+    manager: &BddManager,
+    bdd: &Bdd,
+    map: &HashMap<usize, usize>,
+    cache: &mut HashMap<Bdd, Bdd>,
+) -> Bdd {
+    if bdd.is_constant(true) || bdd.is_constant(false) {
+        return bdd.clone();
+    }
+    if let Some(res) = cache.get(bdd) {
+        return res.clone();
+    }
+    let var = bdd.var();
+    let new_var = map.get(&(var / 2 * 2)).map_or(var, |current| current + var % 2);
+    let high = permute(manager, &bdd.high(), map, cache);
+    let low = permute(manager, &bdd.low(), map, cache);
+    let var = manager.ith_var(new_var);
+    let res = (&var & high) | (!var & low);
+    cache.insert(bdd.clone(), res.clone());
+    res
+}
+
+/// A BDD variable order of the state symbols, from the top of the BDDs to the bottom.
+#[derive(Debug, Clone)]
+pub struct VarOrder {
+    pub names: Vec<String>,
+}
+
+impl VarOrder {
+    pub fn current(symbols: &HashMap<String, usize>) -> Self {
+        let mut names: Vec<&String> = symbols.keys().collect();
+        names.sort_by_key(|name| symbols[*name]);
+        Self {
+            names: names.into_iter().cloned().collect(),
+        }
+    }
+
+    /// Reads a NuSMV order file, one variable per line.
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let names = fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.split("--").next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect();
+        Ok(Self { names })
+    }
+
+    pub fn write_file(&self, path: &str) -> io::Result<()> {
+        let mut content = self.names.join("\n");
+        content.push('\n');
+        fs::write(path, content)
+    }
+
+    /// Static ordering with the FORCE heuristic, placing the variables of each transition
+    /// conjunct close to their center of gravity.
+    pub fn force(fsmbdd: &FsmBdd<BddManager>) -> Self {
+        Self::force_with(fsmbdd, &[])
+    }
+
+    /// FORCE ordering where the supports of `extra` are also kept close together.
+    pub fn force_with(fsmbdd: &FsmBdd<BddManager>, extra: &[Bdd]) -> Self {
+        let order = Self::current(&fsmbdd.symbols);
+        let ids: HashMap<usize, &String> = fsmbdd
+            .symbols
+            .iter()
+            .map(|(name, id)| (*id, name))
+            .collect();
+        let edges: Vec<Vec<&String>> = fsmbdd
+            .trans
+            .trans
+            .iter()
+            .chain(extra.iter())
+            .map(|trans| {
+                support(trans)
+                    .into_iter()
+                    .filter_map(|var| ids.get(&var).copied())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|edge| edge.len() > 1)
+            .collect();
+        let span = |position: &HashMap<&String, f64>| -> f64 {
+            edges
+                .iter()
+                .map(|edge| {
+                    let positions = edge.iter().map(|name| position[name]);
+                    let max = positions.clone().fold(f64::MIN, f64::max);
+                    let min = positions.fold(f64::MAX, f64::min);
+                    max - min
+                })
+                .sum()
+        };
+        let mut names: Vec<&String> = order.names.iter().collect();
+        let mut position: HashMap<&String, f64> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, i as f64))
+            .collect();
+        let mut best = span(&position);
+        for _ in 0..50 {
+            let mut sum: HashMap<&String, (f64, usize)> = HashMap::new();
+            for edge in edges.iter() {
+                let center =
+                    edge.iter().map(|name| position[name]).sum::<f64>() / edge.len() as f64;
+                for name in edge.iter() {
+                    let entry = sum.entry(*name).or_insert((0.0, 0));
+                    entry.0 += center;
+                    entry.1 += 1;
+                }
+            }
+            let mut new_names = names.clone();
+            new_names.sort_by(|a, b| {
+                let key = |name: &&String| {
+                    sum.get(name)
+                        .map_or(position[name], |(sum, num)| sum / *num as f64)
+                };
+                key(a).total_cmp(&key(b))
+            });
+            let new_position: HashMap<&String, f64> = new_names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (*name, i as f64))
+                .collect();
+            let new_span = span(&new_position);
+            if new_span >= best {
+                break;
+            }
+            best = new_span;
+            names = new_names;
+            position = new_position;
+        }
+        Self {
+            names: names.into_iter().cloned().collect(),
+        }
+    }
+
+    /// Maps the current state variable of every symbol to its position in this order.
+    /// Symbols missing from the order keep their relative order after the listed ones.
+    pub fn permutation(&self, symbols: &HashMap<String, usize>) -> HashMap<usize, usize> {
+        let mut names: Vec<&String> = self
+            .names
+            .iter()
+            .filter(|name| symbols.contains_key(*name))
+            .collect();
+        for name in Self::current(symbols).names.iter() {
+            if !self.names.contains(name) {
+                names.push(symbols.get_key_value(name).unwrap().0);
+            }
+        }
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (symbols[name], i * 2))
+            .collect()
+    }
+
+    /// Rebuilds the model and the defines over it in this order.
+    pub fn apply(
+        &self,
+        fsmbdd: &FsmBdd<BddManager>,
+        defines: &HashMap<String, Bdd>,
+        method: TransBddMethod,
+    ) -> (FsmBdd<BddManager>, HashMap<String, Bdd>) {
+        let map = self.permutation(&fsmbdd.symbols);
+        let manager = &fsmbdd.manager;
+        let mut cache = HashMap::new();
+        let mut rename = |bdd: &Bdd| permute(manager, bdd, &map, &mut cache);
+        let trans = fsmbdd.trans.trans.iter().map(&mut rename).collect();
+        let fsmbdd = FsmBdd {
+            symbols: fsmbdd
+                .symbols
+                .iter()
+                .map(|(name, id)| (name.clone(), map[id]))
+                .collect(),
+            manager: manager.clone(),
+            init: rename(&fsmbdd.init),
+            invariants: rename(&fsmbdd.invariants),
+            trans: Trans::new(manager, trans, method),
+            justice: fsmbdd.justice.iter().map(&mut rename).collect(),
+        };
+        let defines = defines
+            .iter()
+            .map(|(name, bdd)| (name.clone(), rename(bdd)))
+            .collect();
+        (fsmbdd, defines)
+    }
+}
+
+/// Applies the variable ordering options to a freshly built model.
+pub fn apply_order_options(
+    args: &Args,
+    fsmbdd: FsmBdd<BddManager>,
+    defines: HashMap<String, Bdd>,
+) -> Result<(FsmBdd<BddManager>, HashMap<String, Bdd>), String> {
+    let order = if let Some(path) = &args.order_file {
+        let order = VarOrder::from_file(path)
+            .map_err(|e| format!("can not read the order file {}: {}", path, e))?;
+        Some(order)
+    } else if args.force_order {
+        Some(VarOrder::force(&fsmbdd))
+    } else {
+        None
+    };
+    let (fsmbdd, defines) = match order {
+        Some(order) => order.apply(&fsmbdd, &defines, args.trans_method.into()),
+        None => (fsmbdd, defines),
+    };
+    if let Some(path) = &args.output_order_file {
+        VarOrder::current(&fsmbdd.symbols)
+            .write_file(path)
+            .map_err(|e| format!("can not write the order file {}: {}", path, e))?;
+    }
+    Ok((fsmbdd, defines))
+}
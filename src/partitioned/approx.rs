@@ -0,0 +1,107 @@
+use super::PartitionedSmc;
+use crate::{order::support, Bdd, BddManager};
+use fsmbdd::{FsmBdd, Trans};
+use std::collections::BTreeSet;
+
+/// Largest number of state variables in the support of a cluster of trans conjuncts.
+const MAX_CLUSTER_VARS: usize = 16;
+
+impl PartitionedSmc {
+    /// Groups the trans conjuncts into clusters of small support, each one a machine whose
+    /// image over-approximates the image of the model on the variables it constrains.
+    fn approx_machines(&self) -> Vec<FsmBdd<BddManager>> {
+        let mut clusters: Vec<(BTreeSet<usize>, Vec<Bdd>)> = Vec::new();
+        for conjunct in self.fsmbdd.trans.trans.iter() {
+            let vars = support(conjunct);
+            let cluster = clusters.iter_mut().find(|(cluster_vars, _)| {
+                !cluster_vars.is_disjoint(&vars)
+                    && cluster_vars.union(&vars).count() <= MAX_CLUSTER_VARS
+            });
+            match cluster {
+                Some((cluster_vars, conjuncts)) => {
+                    cluster_vars.extend(vars);
+                    conjuncts.push(conjunct.clone());
+                }
+                None => clusters.push((vars, vec![conjunct.clone()])),
+            }
+        }
+        clusters
+            .into_iter()
+            .map(|(_, conjuncts)| FsmBdd {
+                symbols: self.fsmbdd.symbols.clone(),
+                manager: self.manager.clone(),
+                init: self.fsmbdd.init.clone(),
+                invariants: self.fsmbdd.invariants.clone(),
+                trans: Trans::new(&self.manager, conjuncts, self.args.trans_method.into()),
+                justice: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Cheap over-approximation of the reachable states of each partition, by a
+    /// machine-by-machine fixpoint: every model image is approximated by the intersection
+    /// of the images of the clusters of trans conjuncts, and only propagated along
+    /// automaton edges whose labels are satisfiable on the approximated states.
+    pub fn approx_reachable(&mut self) -> Vec<Bdd> {
+        let machines = self.approx_machines();
+        let image = |states: &Bdd| {
+            machines
+                .iter()
+                .fold(self.manager.constant(true), |image, machine| {
+                    image & machine.post_image(states)
+                })
+        };
+        let mut approx = vec![self.manager.constant(false); self.automata.num_state()];
+        let mut queue = Vec::new();
+        for init_state in self.automata.init_states.iter() {
+            approx[*init_state] |= &self.fsmbdd.init;
+            queue.push(*init_state);
+        }
+        while let Some(state) = queue.pop() {
+            for (next, label) in self.automata.forward[state].iter() {
+                let source = &approx[state] & label;
+                if source.is_constant(false) {
+                    continue;
+                }
+                let update = &approx[*next] | image(&source);
+                if update != approx[*next] {
+                    approx[*next] = update;
+                    queue.push(*next);
+                }
+            }
+        }
+        self.statistic.approx_pruned_partitions =
+            approx.iter().filter(|bdd| bdd.is_constant(false)).count();
+        approx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartitionedSmc;
+    use crate::{aiger::Aiger, automata::BuchiAutomata, command::Args};
+    use clap::Parser;
+    use fsmbdd::TransBddMethod;
+
+    #[test]
+    fn prunes_unreachable_partition() {
+        let manager = crate::test_manager();
+        // l0 stays 0, l1 toggles
+        let aiger = Aiger::parse(b"aag 2 0 2 0 0\n2 2\n4 5\n").unwrap();
+        let (fsmbdd, _) = aiger.to_fsmbdd(&manager, TransBddMethod::Partition);
+        let l0 = manager.ith_var(fsmbdd.symbols["l0"]);
+        let mut automata = BuchiAutomata::new(manager.clone());
+        automata.add_init_state(0);
+        automata.add_edge(0, 0, manager.constant(true));
+        automata.add_edge(0, 1, l0.clone());
+        automata.add_edge(1, 1, manager.constant(true));
+        automata.add_accepting_state(1);
+        let args = Args::parse_from(["partitioned-smc", "--approx-prune"]);
+        let mut smc = PartitionedSmc::new(manager, fsmbdd, automata, args);
+        let approx = smc.approx_reachable();
+        // the range of the trans would allow l0 = 1 and enter partition 1
+        assert!(approx[0] == !&l0);
+        assert!(approx[1].is_constant(false));
+        assert_eq!(smc.statistic.approx_pruned_partitions, 1);
+    }
+}
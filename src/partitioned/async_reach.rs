@@ -0,0 +1,201 @@
+use super::PartitionedSmc;
+use crate::{budget::LimitExceeded, command::ParallelMode, Bdd};
+use std::{
+    collections::VecDeque,
+    mem,
+    sync::{Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use sylvan::lace_run;
+
+/// Work queue of the asynchronous post reachability, shared by the imaging threads.
+struct WorkQueue {
+    empty: Bdd,
+    reach: Vec<Bdd>,
+    /// states whose successors belong to the partition and are not imaged yet
+    pending: Vec<Bdd>,
+    /// states whose successors have been scheduled for the partition so far
+    scheduled: Vec<Bdd>,
+    queue: VecDeque<usize>,
+    /// partitions in the queue or being imaged
+    active: Vec<bool>,
+    /// images of every partition so far
+    images: Vec<usize>,
+    busy: usize,
+    idle: Duration,
+    stop: bool,
+}
+
+/// Successors of the states `new` along `edges`, without the states of `scheduled`, which
+/// holds the states already scheduled for the target of every edge.
+fn successors(edges: &[(usize, Bdd)], new: &Bdd, scheduled: &[Bdd]) -> Vec<(usize, Bdd)> {
+    edges
+        .iter()
+        .zip(scheduled.iter())
+        .map(|((next, label), scheduled)| (*next, new & label & !scheduled))
+        .filter(|(_, update)| !update.is_constant(false))
+        .collect()
+}
+
+impl WorkQueue {
+    /// Merges the new states of partition `state` and the successors computed from them.
+    fn merge(&mut self, state: usize, new: &Bdd, successors: Vec<(usize, Bdd)>) {
+        self.reach[state] |= new;
+        for (next, update) in successors {
+            self.scheduled[next] |= &update;
+            self.pending[next] |= update;
+            if !self.active[next] {
+                self.active[next] = true;
+                self.queue.push_back(next);
+            }
+        }
+    }
+
+    /// Images partitions until the queue is empty and no thread is imaging, the global
+    /// quiescence, or until stopped. Every image and the successors of its new states are
+    /// computed without the lock, on snapshots of the reachable and scheduled states, and
+    /// the lock is only taken again to merge them. The BDD operations are handed to Lace
+    /// with `lace_run`, as Sylvan may only be called from Lace workers, while the waits
+    /// for work happen outside of Lace so that no worker blocks the garbage collection.
+    fn run(
+        queue: &Mutex<WorkQueue>,
+        wake: &Condvar,
+        forward: &[Vec<(usize, Bdd)>],
+        image: impl Fn(usize, &Bdd) -> Bdd,
+    ) {
+        let mut guard = queue.lock().unwrap();
+        while !guard.stop {
+            let Some(state) = guard.queue.pop_front() else {
+                if guard.busy == 0 {
+                    guard.stop = true;
+                    wake.notify_all();
+                    break;
+                }
+                let start = Instant::now();
+                guard = wake.wait(guard).unwrap();
+                guard.idle += start.elapsed();
+                continue;
+            };
+            let empty = guard.empty.clone();
+            let states = mem::replace(&mut guard.pending[state], empty);
+            // no other thread images this partition while it is active, so its reachable
+            // states only change in the merge below
+            let reach = guard.reach[state].clone();
+            let scheduled: Vec<Bdd> = forward[state]
+                .iter()
+                .map(|(next, _)| guard.scheduled[*next].clone())
+                .collect();
+            guard.busy += 1;
+            drop(guard);
+            let (new, successors) = lace_run(|_| {
+                let new = image(state, &states) & !&reach;
+                let successors = successors(&forward[state], &new, &scheduled);
+                (new, successors)
+            });
+            guard = queue.lock().unwrap();
+            guard.busy -= 1;
+            guard.images[state] += 1;
+            let work = &mut *guard;
+            lace_run(|_| work.merge(state, &new, successors));
+            if guard.pending[state].is_constant(false) {
+                guard.active[state] = false;
+            } else {
+                guard.queue.push_back(state);
+            }
+            wake.notify_all();
+        }
+    }
+}
+
+impl PartitionedSmc {
+    /// Post reachability without a barrier between iterations: a partition is imaged
+    /// again as soon as new states arrive in it, on `parallel` threads, or on a single
+    /// one in the sequential parallel mode. Meanwhile the budget and the safety part are
+    /// checked on snapshots of the reachable states. The largest number of images of a
+    /// partition counts as the post iterations, to compare with the barrier variant.
+    pub fn async_post_reachable(
+        &mut self,
+        from: &[Bdd],
+        constraint: &[Bdd],
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
+        let num_state = from.len();
+        // partitions pruned by the over-approximation are never entered
+        let forward: Vec<Vec<(usize, Bdd)>> = self
+            .automata
+            .forward
+            .iter()
+            .map(|edges| {
+                edges
+                    .iter()
+                    .filter(|(next, _)| !constraint[*next].is_constant(false))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        let mut work = WorkQueue {
+            empty: self.manager.constant(false),
+            reach: vec![self.manager.constant(false); num_state],
+            pending: vec![self.manager.constant(false); num_state],
+            scheduled: vec![self.manager.constant(false); num_state],
+            queue: VecDeque::new(),
+            active: vec![false; num_state],
+            images: vec![0; num_state],
+            busy: 0,
+            idle: Duration::ZERO,
+            stop: false,
+        };
+        lace_run(|_| {
+            for (state, states) in from.iter().enumerate() {
+                let scheduled: Vec<Bdd> = forward[state]
+                    .iter()
+                    .map(|(next, _)| work.scheduled[*next].clone())
+                    .collect();
+                let successors = successors(&forward[state], states, &scheduled);
+                work.merge(state, states, successors);
+            }
+        });
+        let queue = Mutex::new(work);
+        let wake = Condvar::new();
+        let workers = self.workers.clone();
+        let iterations = self.statistic.post_iterations;
+        let threads = match self.args.parallel_mode {
+            ParallelMode::Sequential => 1,
+            ParallelMode::Lace => self.args.parallel.max(1),
+        };
+        let res = thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    WorkQueue::run(&queue, &wake, &forward, |state, states| {
+                        workers[state].post_image(states)
+                    })
+                });
+            }
+            loop {
+                let guard = queue.lock().unwrap();
+                let (mut guard, _) = wake.wait_timeout(guard, Duration::from_millis(10)).unwrap();
+                if guard.stop && guard.busy == 0 {
+                    break Ok(());
+                }
+                let reach = guard.reach.clone();
+                let rounds = guard.images.iter().max().copied().unwrap_or(0);
+                drop(guard);
+                self.statistic.post_iterations = iterations + rounds;
+                let (res, found) =
+                    lace_run(|_| (self.check_budget(&reach), self.found_bug(&reach)));
+                if res.is_err() || found {
+                    guard = queue.lock().unwrap();
+                    guard.stop = true;
+                    wake.notify_all();
+                    break res;
+                }
+            }
+        });
+        let work = queue.into_inner().unwrap();
+        let rounds = work.images.iter().max().copied().unwrap_or(0);
+        self.statistic.post_iterations = iterations + rounds;
+        self.statistic.async_images += work.images.iter().sum::<usize>();
+        self.statistic.async_idle_time += work.idle;
+        res.map(|_| work.reach)
+    }
+}
@@ -1,18 +1,20 @@
 use super::PartitionedSmc;
-use crate::Bdd;
+use crate::{budget::LimitExceeded, Bdd};
 use sylvan::LaceWorkerContext;
 
 impl PartitionedSmc {
-    pub fn fair_states(&mut self, init_reach: &[Bdd]) -> Vec<Bdd> {
+    pub fn fair_states(&mut self, init_reach: &[Bdd]) -> Result<Vec<Bdd>, LimitExceeded> {
         let mut fair_states = vec![self.manager.constant(false); self.automata.num_state()];
-        for state in self.automata.accepting_states.iter() {
+        for state in self.accepting.iter() {
             fair_states[*state] = init_reach[*state].clone();
         }
         let mut x = 0;
         loop {
             x += 1;
             dbg!(x);
-            let backward = self.pre_reachable(&fair_states, init_reach);
+            self.statistic.fair_iterations += 1;
+            self.reorder();
+            let backward = self.pre_reachable(&fair_states, init_reach)?;
             let mut new_fair_states = Vec::new();
             for i in 0..fair_states.len() {
                 new_fair_states.push(&fair_states[i] & &backward[i]);
@@ -22,23 +24,25 @@ impl PartitionedSmc {
             }
             fair_states = new_fair_states;
         }
-        fair_states
+        Ok(fair_states)
     }
 
     pub fn lace_fair_states(
         &mut self,
         mut context: LaceWorkerContext,
         init_reach: &[Bdd],
-    ) -> Vec<Bdd> {
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
         let mut fair_states = vec![self.manager.constant(false); self.automata.num_state()];
-        for state in self.automata.accepting_states.iter() {
+        for state in self.accepting.iter() {
             fair_states[*state] = init_reach[*state].clone();
         }
         let mut x = 0;
         loop {
             x += 1;
             dbg!(x);
-            let backward = self.lace_pre_reachable(context, &fair_states, init_reach);
+            self.statistic.fair_iterations += 1;
+            self.reorder();
+            let backward = self.lace_pre_reachable(context, &fair_states, init_reach)?;
             fair_states.iter().zip(backward.iter()).for_each(|(x, y)| {
                 let x = x.clone();
                 let y = y.clone();
@@ -50,6 +54,6 @@ impl PartitionedSmc {
             }
             fair_states = new_fair_states;
         }
-        fair_states
+        Ok(fair_states)
     }
 }
@@ -0,0 +1,38 @@
+use super::{worker::Worker, PartitionedSmc};
+use crate::{command::ParallelMode, Bdd};
+use sylvan::lace_run;
+
+impl PartitionedSmc {
+    fn images(&self, states: &[Bdd], job: fn(&Worker, &Bdd) -> Bdd, pre: bool) -> Vec<Bdd> {
+        match self.args.parallel_mode {
+            ParallelMode::Sequential => states
+                .iter()
+                .map(|x| {
+                    if pre {
+                        self.fsmbdd.pre_image(x)
+                    } else {
+                        self.fsmbdd.post_image(x)
+                    }
+                })
+                .collect(),
+            ParallelMode::Lace => lace_run(|mut context| {
+                for (i, x) in states.iter().enumerate() {
+                    let worker = self.workers[i].clone();
+                    let x = x.clone();
+                    context.lace_spawn(move |_| job(&worker, &x));
+                }
+                context.lace_sync_multi(states.len())
+            }),
+        }
+    }
+
+    /// Model post image of every partition.
+    pub fn post_images(&self, states: &[Bdd]) -> Vec<Bdd> {
+        self.images(states, Worker::post_image, false)
+    }
+
+    /// Model pre image of every partition.
+    pub fn pre_images(&self, states: &[Bdd]) -> Vec<Bdd> {
+        self.images(states, Worker::pre_image, true)
+    }
+}
@@ -1,10 +1,24 @@
+mod approx;
+mod async_reach;
 mod fair;
+mod images;
 mod reachable;
+mod safety;
 mod statistic;
+mod strength;
 mod worker;
 
-use self::{statistic::Statistic, worker::Worker};
-use crate::{automata::BuchiAutomata, command::Args, ltl::ltl_to_automata_preprocess, BddManager};
+use self::{safety::Safety, statistic::Statistic, worker::Worker};
+use crate::{
+    automata::{BuchiAutomata, Strength},
+    budget::{Budget, LimitExceeded},
+    command::{Args, ParallelMode},
+    export::dump_automaton,
+    ltl::{ltl_automata, ltl_spec},
+    order::apply_order_options,
+    verdict::Verdict,
+    Bdd, BddManager,
+};
 use fsmbdd::FsmBdd;
 use smv::{bdd::SmvBdd, Expr, Prefix, Smv};
 use std::{
@@ -20,6 +34,10 @@ pub struct PartitionedSmc {
     args: Args,
     statistic: Statistic,
     workers: Vec<Arc<Worker>>,
+    budget: Budget,
+    safety: Option<Safety>,
+    /// Accepting states left to the fair cycle detection, without the safety part.
+    accepting: Vec<usize>,
 }
 
 impl PartitionedSmc {
@@ -29,10 +47,13 @@ impl PartitionedSmc {
         automata: BuchiAutomata,
         args: Args,
     ) -> Self {
-        let workers = Worker::create_workers(&fsmbdd, &automata)
+        let local_order = args.worker_order.then_some(args.trans_method);
+        let workers = Worker::create_workers(&fsmbdd, &automata, local_order)
             .into_iter()
             .map(Arc::new)
             .collect();
+        let budget = Budget::new(&args);
+        let accepting = automata.accepting_states.clone();
         Self {
             manager,
             fsmbdd,
@@ -40,38 +61,124 @@ impl PartitionedSmc {
             args,
             workers,
             statistic: Statistic::default(),
+            budget,
+            safety: None,
+            accepting,
+        }
+    }
+
+    /// Checks the time and memory budget between fixpoint iterations, recording the
+    /// largest partition seen so far when running verbose or under a memory limit.
+    fn check_budget(&mut self, partitions: &[Bdd]) -> Result<(), LimitExceeded> {
+        if self.args.verbose || self.budget.has_memory_limit() {
+            let largest = partitions.iter().map(|bdd| bdd.node_count()).max();
+            self.statistic.largest_partition_nodes = self
+                .statistic
+                .largest_partition_nodes
+                .max(largest.unwrap_or(0));
+        }
+        self.budget.check()
+    }
+
+    /// Dynamic variable reordering between fixpoint iterations.
+    fn reorder(&mut self) {
+        if self.args.dynamic_reorder {
+            let start = Instant::now();
+            self.manager.reduce_heap();
+            self.statistic.reorder_time += start.elapsed();
+        }
+    }
+
+    fn backward_check(&mut self, constraint: Vec<Bdd>) -> Result<bool, LimitExceeded> {
+        let start = Instant::now();
+        let fair_states = if self.args.parallel_mode != ParallelMode::Lace {
+            self.fair_states(&constraint)
+        } else {
+            lace_run(|context| self.lace_fair_states(context, &constraint))
+        }?;
+        self.statistic.fair_cycle_time += start.elapsed();
+        let start = Instant::now();
+        let backward = if self.args.parallel_mode != ParallelMode::Lace {
+            self.pre_reachable(&fair_states, &constraint)
+        } else {
+            lace_run(|context| self.lace_pre_reachable(context, &fair_states, &constraint))
+        }?;
+        self.statistic.init_backward_time += start.elapsed();
+        for init_state in self.automata.init_states.iter() {
+            let fair = &fair_states[*init_state] | &backward[*init_state];
+            if &self.fsmbdd.init & fair != self.manager.constant(false) {
+                return Ok(false);
+            }
         }
+        Ok(true)
     }
 
-    pub fn check(&mut self) -> bool {
+    fn fair_check(&mut self) -> Result<bool, LimitExceeded> {
         let mut reach = vec![self.manager.constant(false); self.automata.num_state()];
         for init_state in self.automata.init_states.iter() {
             reach[*init_state] |= &self.fsmbdd.init;
         }
-        let start = Instant::now();
-        reach = if self.args.close_lace_optimize {
-            self.post_reachable(&reach)
+        let approx = if self.args.approx_prune {
+            let start = Instant::now();
+            let approx = self.approx_reachable();
+            self.statistic.approx_time += start.elapsed();
+            Some(approx)
         } else {
-            lace_run(|context| self.lace_post_reachable(context, &reach))
+            None
         };
-        self.statistic.post_reachable_time += start.elapsed();
+        let constraint = approx.unwrap_or_else(|| {
+            vec![self.manager.constant(true); self.automata.num_state()]
+        });
+        if self.args.backward_only {
+            return self.backward_check(constraint);
+        }
+        if !self.args.no_safety_split {
+            self.split_safety(&constraint)?;
+        }
         let start = Instant::now();
-        let fair_states = if self.args.close_lace_optimize {
-            self.fair_states(&reach)
+        reach = if self.args.async_reach {
+            self.async_post_reachable(&reach, &constraint)
+        } else if self.args.parallel_mode != ParallelMode::Lace {
+            self.post_reachable(&reach, &constraint)
         } else {
-            lace_run(|context| self.lace_fair_states(context, &reach))
-        };
+            lace_run(|context| self.lace_post_reachable(context, &reach, &constraint))
+        }?;
+        self.statistic.post_reachable_time += start.elapsed();
+        if self.found_bug(&reach) {
+            return Ok(false);
+        }
+        if self.accepting.is_empty() {
+            return Ok(true);
+        }
+        let start = Instant::now();
+        let strength = self.automata.strength(&self.accepting);
+        self.statistic.strength = Some(strength);
+        let fair_states = match strength {
+            Strength::Terminal => self.terminal_fair_states(&reach),
+            Strength::Weak => self.weak_fair_states(&reach),
+            Strength::Strong if self.args.parallel_mode == ParallelMode::Lace => {
+                lace_run(|context| self.lace_fair_states(context, &reach))
+            }
+            Strength::Strong => self.fair_states(&reach),
+        }?;
         self.statistic.fair_cycle_time += start.elapsed();
-        for accept in self.automata.accepting_states.iter() {
+        for accept in self.accepting.iter() {
             if &reach[*accept] & &fair_states[*accept] != self.manager.constant(false) {
-                return false;
+                return Ok(false);
             }
         }
-        true
+        Ok(true)
+    }
+
+    pub fn check(&mut self) -> Verdict {
+        match self.fair_check() {
+            Ok(holds) => Verdict::from_holds(holds),
+            Err(limit) => limit.into(),
+        }
     }
 }
 
-fn get_ltl(smv: &Smv, extend_trans: &[usize]) -> Expr {
+pub fn get_ltl(smv: &Smv, property: usize, extend_trans: &[usize]) -> Result<Expr, String> {
     dbg!(&smv.trans.len());
     dbg!(extend_trans);
     // let smv = smv.flatten_defines();
@@ -88,27 +195,38 @@ fn get_ltl(smv: &Smv, extend_trans: &[usize]) -> Expr {
         );
         fairness = fairness & fair;
     }
-    let ltl = smv.ltlspecs[0].clone();
-    let ltl = !Expr::InfixExpr(
+    let ltl = ltl_spec(smv, property)?;
+    Ok(!Expr::InfixExpr(
         smv::Infix::Imply,
         Box::new(trans_ltl & fairness),
         Box::new(ltl),
-    );
-    let ltl = ltl_to_automata_preprocess(&smv, ltl);
-    println!("{}", ltl);
-    ltl
+    ))
 }
 
-pub fn check(manager: BddManager, smv: Smv, args: Args) -> (bool, Duration) {
+pub fn check(manager: BddManager, smv: Smv, args: Args) -> (Verdict, Duration) {
     let smv_bdd = SmvBdd::new(&manager, &smv);
     let mut fsmbdd = smv_bdd.to_fsmbdd(args.trans_method.into());
     fsmbdd.justice.clear();
-    let ba = BuchiAutomata::from_ltl(
-        get_ltl(&smv, &args.ltl_extend_trans),
-        &manager,
-        &smv_bdd.symbols,
-        &smv_bdd.defines,
-    );
+    let (mut fsmbdd, defines) = match apply_order_options(&args, fsmbdd, smv_bdd.defines.clone()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    let ba = get_ltl(&smv, args.property.index, &args.ltl_extend_trans)
+        .and_then(|ltl| ltl_automata(&smv, &ltl, &mut fsmbdd, &defines, &args));
+    let ba = match ba {
+        Ok(ba) => ba,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    check_automata(manager, fsmbdd, ba, args)
+}
+
+pub fn check_automata(
+    manager: BddManager,
+    fsmbdd: FsmBdd<BddManager>,
+    ba: BuchiAutomata,
+    args: Args,
+) -> (Verdict, Duration) {
+    dump_automaton(&args, &ba);
     let mut partitioned_smc = PartitionedSmc::new(manager, fsmbdd, ba, args);
     dbg!("partitioned smc start checking");
     let start = Instant::now();
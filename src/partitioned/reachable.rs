@@ -1,10 +1,40 @@
 use super::PartitionedSmc;
-use crate::Bdd;
-use std::{sync::Arc, time::Instant};
+use crate::{budget::LimitExceeded, Bdd};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use sylvan::LaceWorkerContext;
 
+/// Minimise `frontier` inside the interval `[frontier, frontier | done]`, where `done`
+/// holds states whose image has already been computed.
+fn simplify_frontier(frontier: &Bdd, done: &Bdd) -> Bdd {
+    if frontier.is_constant(false) {
+        return frontier.clone();
+    }
+    frontier.restrict(&(frontier | !done))
+}
+
 impl PartitionedSmc {
-    pub fn post_reachable(&mut self, from: &[Bdd]) -> Vec<Bdd> {
+    fn simplify_frontiers(&mut self, frontier: &mut [Bdd], done: &[Bdd]) {
+        if !self.args.frontier_simplify {
+            return;
+        }
+        for i in 0..frontier.len() {
+            let simplified = simplify_frontier(&frontier[i], &done[i]);
+            self.statistic.frontier_nodes_before_simplify += frontier[i].node_count();
+            self.statistic.frontier_nodes_after_simplify += simplified.node_count();
+            frontier[i] = simplified;
+        }
+    }
+
+    /// Post reachability from `from`, never entering the partitions whose `constraint` is
+    /// false.
+    pub fn post_reachable(
+        &mut self,
+        from: &[Bdd],
+        constraint: &[Bdd],
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
         let partitioned_len = from.len();
         let mut frontier = from.to_vec();
         let mut reach = frontier.clone();
@@ -15,18 +45,28 @@ impl PartitionedSmc {
             if self.args.verbose {
                 dbg!(post_deep);
             }
+            self.check_budget(&reach)?;
+            if self.found_bug(&reach) {
+                break Ok(reach);
+            }
+            self.statistic.post_iterations += 1;
             let mut tmp = vec![self.manager.constant(false); self.automata.num_state()];
             for i in 0..frontier.len() {
                 for (next, label) in self.automata.forward[i].iter() {
+                    if constraint[*next].is_constant(false) {
+                        continue;
+                    }
                     let update = &frontier[i] & label & !&reach_tmp[*next];
                     tmp[*next] |= &update;
                     reach_tmp[*next] |= update;
                 }
             }
             if tmp.iter().all(|bdd| bdd.is_constant(false)) {
-                break reach;
+                break Ok(reach);
             }
-            let image: Vec<Bdd> = tmp.iter().map(|x| self.fsmbdd.post_image(x)).collect();
+            self.simplify_frontiers(&mut tmp, &reach_tmp);
+            self.reorder();
+            let image = self.post_images(&tmp);
             for i in 0..image.len() {
                 reach[i] |= &image[i];
             }
@@ -34,7 +74,11 @@ impl PartitionedSmc {
         }
     }
 
-    pub fn pre_reachable(&mut self, from: &[Bdd], constraint: &[Bdd]) -> Vec<Bdd> {
+    pub fn pre_reachable(
+        &mut self,
+        from: &[Bdd],
+        constraint: &[Bdd],
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
         let mut frontier = from.to_vec();
         let mut reach = vec![self.manager.constant(false); self.automata.num_state()];
         let mut y = 0;
@@ -43,10 +87,16 @@ impl PartitionedSmc {
             if self.args.verbose {
                 dbg!(y);
             }
+            self.check_budget(&reach)?;
+            self.statistic.pre_iterations += 1;
             let mut new_frontier = vec![self.manager.constant(false); self.automata.num_state()];
-            let image: Vec<Bdd> = frontier.iter().map(|x| self.fsmbdd.pre_image(x)).collect();
+            self.simplify_frontiers(&mut frontier, &reach);
+            let image = self.pre_images(&frontier);
             for i in 0..frontier.len() {
                 for (next, label) in self.automata.backward[i].iter() {
+                    if constraint[*next].is_constant(false) {
+                        continue;
+                    }
                     let mut update = &image[i] & label & &constraint[*next];
                     update &= !&reach[*next];
                     new_frontier[*next] = &new_frontier[*next] | &update;
@@ -58,7 +108,7 @@ impl PartitionedSmc {
             }
             frontier = new_frontier;
         }
-        reach
+        Ok(reach)
     }
 }
 
@@ -67,7 +117,8 @@ impl PartitionedSmc {
         &mut self,
         mut context: LaceWorkerContext,
         from: &[Bdd],
-    ) -> Vec<Bdd> {
+        constraint: &[Bdd],
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
         let mut frontier = from.to_vec();
         let partitioned_len = from.len();
         let mut reach = frontier.clone();
@@ -78,35 +129,51 @@ impl PartitionedSmc {
             if self.args.verbose {
                 dbg!(post_deep);
             }
+            self.check_budget(&reach)?;
+            if self.found_bug(&reach) {
+                break Ok(reach);
+            }
+            self.statistic.post_iterations += 1;
             let start = Instant::now();
             let mut tmp = vec![self.manager.constant(false); partitioned_len];
             for i in 0..partitioned_len {
                 for (next, label) in self.automata.forward[i].iter() {
+                    if constraint[*next].is_constant(false) {
+                        continue;
+                    }
                     let update = &frontier[i] & label & !&tmp_reach[*next];
                     tmp[*next] |= &update;
                     tmp_reach[*next] |= update;
                 }
             }
             if tmp.iter().all(|bdd| bdd.is_constant(false)) {
-                break reach;
+                break Ok(reach);
             }
+            self.simplify_frontiers(&mut tmp, &tmp_reach);
             self.statistic.post_propagate_time += start.elapsed();
+            self.reorder();
             let start = Instant::now();
             for i in 0..partitioned_len {
                 let bdd = tmp[i].clone();
                 let mut reach = reach[i].clone();
                 let worker = self.workers[i].clone();
                 context.lace_spawn(move |_| {
-                    let image = worker.fsmbdd.post_image(&bdd);
+                    let start = Instant::now();
+                    let image = worker.post_image(&bdd);
                     reach |= &image;
-                    (reach, image)
+                    (reach, image, start.elapsed())
                 });
             }
-            let reach_update: Vec<(Bdd, Bdd)> = context.lace_sync_multi(partitioned_len);
-            self.statistic.post_image_time += start.elapsed();
+            let reach_update: Vec<(Bdd, Bdd, Duration)> = context.lace_sync_multi(partitioned_len);
+            let wall = start.elapsed();
+            self.statistic.post_image_time += wall;
+            // time the lace workers spend without an image to compute until the barrier
+            let busy: Duration = reach_update.iter().map(|(_, _, time)| *time).sum();
+            let capacity = wall * self.args.parallel.max(1) as u32;
+            self.statistic.barrier_idle_time += capacity.saturating_sub(busy);
             frontier.clear();
             reach = Vec::new();
-            for (reach_bdd, update) in reach_update {
+            for (reach_bdd, update, _) in reach_update {
                 reach.push(reach_bdd);
                 frontier.push(update);
             }
@@ -124,6 +191,7 @@ impl PartitionedSmc {
     ) -> (Vec<Bdd>, Vec<Bdd>) {
         let partitioned_len = states.len();
         let states = Arc::new(states);
+        let frontier_simplify = self.args.frontier_simplify;
         for i in 0..partitioned_len {
             let worker = self.workers[i].clone();
             let reach = reach[i].clone();
@@ -131,18 +199,26 @@ impl PartitionedSmc {
             let constraint = constraint[i].clone();
             context.lace_spawn(move |_| {
                 let (reach, mut new_frontier) = worker.propagate(reach, states, constraint);
+                let mut nodes = (0, 0);
+                if frontier_simplify {
+                    let simplified = simplify_frontier(&new_frontier, &reach);
+                    nodes = (new_frontier.node_count(), simplified.node_count());
+                    new_frontier = simplified;
+                }
                 if !new_frontier.is_constant(false) {
-                    new_frontier = worker.fsmbdd.pre_image(&new_frontier);
+                    new_frontier = worker.pre_image(&new_frontier);
                 }
-                (reach, new_frontier)
+                (reach, new_frontier, nodes)
             })
         }
-        let res = context.lace_sync_multi::<(Bdd, Bdd)>(partitioned_len);
+        let res = context.lace_sync_multi::<(Bdd, Bdd, (usize, usize))>(partitioned_len);
         let mut reach = Vec::new();
         let mut new_frontier = Vec::new();
-        for (r, f) in res.into_iter() {
+        for (r, f, (before, after)) in res.into_iter() {
             reach.push(r);
             new_frontier.push(f);
+            self.statistic.frontier_nodes_before_simplify += before;
+            self.statistic.frontier_nodes_after_simplify += after;
         }
         (reach, new_frontier)
     }
@@ -152,7 +228,7 @@ impl PartitionedSmc {
         mut context: LaceWorkerContext,
         from: &[Bdd],
         constraint: &[Bdd],
-    ) -> Vec<Bdd> {
+    ) -> Result<Vec<Bdd>, LimitExceeded> {
         let partitioned_len = from.len();
         let mut frontier = from.to_vec();
         let mut reach = vec![self.manager.constant(false); partitioned_len];
@@ -160,7 +236,7 @@ impl PartitionedSmc {
         for i in 0..partitioned_len {
             let worker = self.workers[i].clone();
             let x = frontier[i].clone();
-            context.lace_spawn(move |_| worker.fsmbdd.pre_image(&x));
+            context.lace_spawn(move |_| worker.pre_image(&x));
         }
         frontier = context.lace_sync_multi(partitioned_len);
         loop {
@@ -168,6 +244,8 @@ impl PartitionedSmc {
             if self.args.verbose {
                 dbg!(y);
             }
+            self.check_budget(&reach)?;
+            self.statistic.pre_iterations += 1;
             let start = Instant::now();
             let new_frontier;
             (reach, new_frontier) = self.lace_pre_iteration(context, frontier, &reach, constraint);
@@ -177,6 +255,6 @@ impl PartitionedSmc {
             }
             frontier = new_frontier;
         }
-        reach
+        Ok(reach)
     }
 }
@@ -0,0 +1,45 @@
+use super::PartitionedSmc;
+use crate::{budget::LimitExceeded, Bdd};
+
+/// Safety part of the automaton, checked during the post reachability.
+pub struct Safety {
+    states: Vec<usize>,
+    /// Model states with an infinite path, within the approximated reachable states.
+    pub live: Bdd,
+}
+
+impl PartitionedSmc {
+    /// Splits the accepting states of the automaton into its safety part, checked for bugs
+    /// while computing the reachable states, and the liveness part left to the fair cycle
+    /// detection. The live states are computed within the union of the `constraint`
+    /// partitions, which contains every reachable state.
+    pub fn split_safety(&mut self, constraint: &[Bdd]) -> Result<(), LimitExceeded> {
+        let states = self.automata.safety_states();
+        self.statistic.safety_states = states.len();
+        if states.is_empty() {
+            return Ok(());
+        }
+        let within = constraint
+            .iter()
+            .fold(self.manager.constant(false), |within, bdd| within | bdd);
+        let live = self.live_states(&within)?;
+        self.accepting.retain(|state| !states.contains(state));
+        self.safety = Some(Safety { states, live });
+        Ok(())
+    }
+
+    /// Whether a reachable state of the safety part has an infinite path.
+    pub fn found_bug(&mut self, reach: &[Bdd]) -> bool {
+        let Some(safety) = &self.safety else {
+            return false;
+        };
+        let found = safety
+            .states
+            .iter()
+            .any(|state| !(&reach[*state] & &safety.live).is_constant(false));
+        if found && self.statistic.early_bug_iteration.is_none() {
+            self.statistic.early_bug_iteration = Some(self.statistic.post_iterations);
+        }
+        found
+    }
+}
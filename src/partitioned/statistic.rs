@@ -1,3 +1,4 @@
+use crate::automata::Strength;
 use std::time::Duration;
 
 #[derive(Debug, Default)]
@@ -9,4 +10,24 @@ pub struct Statistic {
     pub pre_image_time: Duration,
     pub pre_propagate_time: Duration,
     pub test_a: Duration,
+    pub frontier_nodes_before_simplify: usize,
+    pub frontier_nodes_after_simplify: usize,
+    pub approx_time: Duration,
+    pub approx_pruned_partitions: usize,
+    pub fair_iterations: usize,
+    pub init_backward_time: Duration,
+    pub reorder_time: Duration,
+    pub post_iterations: usize,
+    pub pre_iterations: usize,
+    pub largest_partition_nodes: usize,
+    pub strength: Option<Strength>,
+    pub safety_states: usize,
+    pub early_bug_iteration: Option<usize>,
+    /// Worker time spent idle in the synchronised post reachability, as the capacity of
+    /// the workers during the images minus the time spent imaging.
+    pub barrier_idle_time: Duration,
+    /// Worker time spent waiting for work in the asynchronous post reachability, to be
+    /// compared with `barrier_idle_time`.
+    pub async_idle_time: Duration,
+    pub async_images: usize,
 }
@@ -0,0 +1,78 @@
+use super::PartitionedSmc;
+use crate::{budget::LimitExceeded, Bdd};
+
+impl PartitionedSmc {
+    /// States of the model with an infinite path staying `within`. From a reachable state
+    /// of the safety or terminal part of the automaton every path of the model is
+    /// reachable, so there this is `EG TRUE` as soon as `within` contains the reachable
+    /// states.
+    pub fn live_states(&mut self, within: &Bdd) -> Result<Bdd, LimitExceeded> {
+        let mut live = within.clone();
+        loop {
+            self.check_budget(&[live.clone()])?;
+            self.statistic.pre_iterations += 1;
+            let new_live = &live & self.fsmbdd.pre_image(&live);
+            if new_live == live {
+                break Ok(live);
+            }
+            live = new_live;
+        }
+    }
+
+    /// Fair states of a terminal automaton: once an accepting state is reached the run can
+    /// stay accepting forever, so it only remains to continue the path of the model.
+    /// The live states computed for the safety part are reused if there is one.
+    pub fn terminal_fair_states(&mut self, reach: &[Bdd]) -> Result<Vec<Bdd>, LimitExceeded> {
+        let live = match &self.safety {
+            Some(safety) => safety.live.clone(),
+            None => {
+                let within = reach
+                    .iter()
+                    .fold(self.manager.constant(false), |within, bdd| within | bdd);
+                self.live_states(&within)?
+            }
+        };
+        let mut fair_states = vec![self.manager.constant(false); self.automata.num_state()];
+        for state in self.accepting.iter() {
+            fair_states[*state] = &reach[*state] & &live;
+        }
+        Ok(fair_states)
+    }
+
+    /// Fair states of a weak automaton: a run is accepting iff it eventually stays in
+    /// accepting states, so a single greatest fixpoint over the accepting partitions
+    /// replaces the nested fixpoint of `fair_states`.
+    pub fn weak_fair_states(&mut self, reach: &[Bdd]) -> Result<Vec<Bdd>, LimitExceeded> {
+        let num_state = self.automata.num_state();
+        let mut accepting = vec![false; num_state];
+        let mut fair_states = vec![self.manager.constant(false); num_state];
+        for state in self.accepting.iter() {
+            accepting[*state] = true;
+            fair_states[*state] = reach[*state].clone();
+        }
+        loop {
+            self.check_budget(&fair_states)?;
+            self.statistic.fair_iterations += 1;
+            self.reorder();
+            let image = self.pre_images(&fair_states);
+            let mut new_fair_states = vec![self.manager.constant(false); num_state];
+            for next in 0..num_state {
+                if !accepting[next] || image[next].is_constant(false) {
+                    continue;
+                }
+                for (state, label) in self.automata.backward[next].iter() {
+                    if accepting[*state] {
+                        new_fair_states[*state] |= &image[next] & label;
+                    }
+                }
+            }
+            for i in 0..num_state {
+                new_fair_states[i] &= &fair_states[i];
+            }
+            if new_fair_states == fair_states {
+                break Ok(fair_states);
+            }
+            fair_states = new_fair_states;
+        }
+    }
+}
@@ -1,20 +1,36 @@
-use crate::{automata::BuchiAutomata, Bdd, BddManager};
+use crate::{
+    automata::BuchiAutomata,
+    command::TransMethod,
+    order::{permute, VarOrder},
+    Bdd, BddManager,
+};
 use fsmbdd::FsmBdd;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+
+/// Variable renaming between the global order and the local order of a worker.
+struct LocalOrder {
+    to_local: HashMap<usize, usize>,
+    to_global: HashMap<usize, usize>,
+}
 
 #[allow(dead_code)]
 pub struct Worker {
     id: usize,
     manager: BddManager,
+    global: BddManager,
     pub fsmbdd: FsmBdd<BddManager>,
     forward: Vec<(usize, Bdd)>,
     backward: Vec<(usize, Bdd)>,
+    order: Option<LocalOrder>,
 }
 
 #[allow(dead_code)]
 impl Worker {
     pub fn propagate(&self, mut reach: Bdd, data: Arc<Vec<Bdd>>, constraint: Bdd) -> (Bdd, Bdd) {
         let mut new_frontier = self.manager.constant(false);
+        if constraint.is_constant(false) {
+            return (reach, new_frontier);
+        }
         for (from, label) in self.forward.iter() {
             let mut update = &data[*from] & label & &constraint;
             update &= !&reach;
@@ -24,18 +40,67 @@ impl Worker {
         (reach, new_frontier)
     }
 
-    pub fn create_workers(fsmbdd: &FsmBdd<BddManager>, automata: &BuchiAutomata) -> Vec<Self> {
+    /// Transfers `bdd` from the global order into the order of this worker.
+    pub fn to_local(&self, bdd: &Bdd) -> Bdd {
+        match &self.order {
+            Some(order) => permute(&self.manager, bdd, &order.to_local, &mut HashMap::new()),
+            None => bdd.clone(),
+        }
+    }
+
+    /// Transfers `bdd` from the order of this worker into the global order.
+    pub fn to_global(&self, bdd: &Bdd) -> Bdd {
+        match &self.order {
+            Some(order) => permute(&self.global, bdd, &order.to_global, &mut HashMap::new()),
+            None => bdd.clone(),
+        }
+    }
+
+    pub fn post_image(&self, bdd: &Bdd) -> Bdd {
+        self.to_global(&self.fsmbdd.post_image(&self.to_local(bdd)))
+    }
+
+    pub fn pre_image(&self, bdd: &Bdd) -> Bdd {
+        self.to_global(&self.fsmbdd.pre_image(&self.to_local(bdd)))
+    }
+
+    /// Creates a worker for every automaton state. With `local_order`, each worker orders
+    /// its variables with FORCE, keeping the supports of the labels of its edges close.
+    pub fn create_workers(
+        fsmbdd: &FsmBdd<BddManager>,
+        automata: &BuchiAutomata,
+        local_order: Option<TransMethod>,
+    ) -> Vec<Self> {
+        let global = &fsmbdd.manager;
         let mut workers = vec![];
         for id in 0..automata.num_state() {
-            let fsmbdd = fsmbdd.clone_with_new_manager();
+            let mut fsmbdd = fsmbdd.clone_with_new_manager();
             let forward = automata.forward[id].clone();
             let backward = automata.backward[id].clone();
+            let mut order = None;
+            if let Some(method) = local_order {
+                let labels: Vec<Bdd> = forward
+                    .iter()
+                    .chain(backward.iter())
+                    .map(|(_, label)| label.clone())
+                    .collect();
+                let var_order = VarOrder::force_with(&fsmbdd, &labels);
+                let to_local = var_order.permutation(&fsmbdd.symbols);
+                let to_global = to_local.iter().map(|(k, v)| (*v, *k)).collect();
+                (fsmbdd, _) = var_order.apply(&fsmbdd, &HashMap::new(), method.into());
+                order = Some(LocalOrder {
+                    to_local,
+                    to_global,
+                });
+            }
             workers.push(Self {
                 id,
                 manager: fsmbdd.manager.clone(),
+                global: global.clone(),
                 fsmbdd,
                 forward,
                 backward,
+                order,
             })
         }
         workers
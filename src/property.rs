@@ -0,0 +1,148 @@
+use crate::{
+    aiger::{self, Aiger},
+    automata::BuchiAutomata,
+    btor2,
+    command::{Algorithm, Args},
+    order::apply_order_options,
+    partitioned, traditional,
+    verdict::Verdict,
+    Bdd, BddManager,
+};
+use fsmbdd::FsmBdd;
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
+
+/// Index of the checked property. For hardware models `b<n>` and `j<n>` select bad or
+/// justice property `n`, and a plain `n` the justice property if there is any and the
+/// bad property otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertyIndex {
+    pub kind: Option<char>,
+    pub index: usize,
+}
+
+impl FromStr for PropertyIndex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, index) = match s.strip_prefix(['b', 'j']) {
+            Some(index) => (s.chars().next(), index),
+            None => (None, s),
+        };
+        let index = index
+            .parse()
+            .map_err(|e| format!("invalid property '{}': {}", s, e))?;
+        Ok(Self { kind, index })
+    }
+}
+
+impl Display for PropertyIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{}{}", kind, self.index),
+            None => write!(f, "{}", self.index),
+        }
+    }
+}
+
+/// Safety and liveness properties of a hardware model. A bad property is violated by a
+/// fair path reaching a bad state, a justice property by a fair path on which all its
+/// constraints hold infinitely often.
+#[derive(Debug, Clone, Default)]
+pub struct Properties {
+    pub bad: Vec<Bdd>,
+    pub justice: Vec<Vec<Bdd>>,
+    pub fairness: Vec<Bdd>,
+}
+
+impl Properties {
+    /// Kind of the property selected by `property`, `b` for bad and `j` for justice.
+    pub fn kind(&self, property: PropertyIndex) -> char {
+        property
+            .kind
+            .unwrap_or(if self.justice.is_empty() { 'b' } else { 'j' })
+    }
+
+    /// The automaton of the violations of the selected property.
+    pub fn automata(
+        &self,
+        manager: &BddManager,
+        symbols: &HashMap<String, usize>,
+        property: PropertyIndex,
+    ) -> Result<BuchiAutomata, String> {
+        let kind = self.kind(property);
+        let missing = || match kind {
+            'b' => format!("the model has no bad property {}", property.index),
+            _ => format!("the model has no justice property {}", property.index),
+        };
+        let (bad, mut justice) = if kind == 'b' {
+            let bad = self.bad.get(property.index).ok_or_else(missing)?;
+            (Some(bad), Vec::new())
+        } else {
+            let justice = self.justice.get(property.index).ok_or_else(missing)?;
+            (None, justice.clone())
+        };
+        justice.extend(self.fairness.iter().cloned());
+        Ok(BuchiAutomata::from_constraints(
+            manager, symbols, bad, &justice,
+        ))
+    }
+}
+
+/// A hardware model in the AIGER or BTOR2 format, keeping the AIGER circuit to print
+/// witnesses.
+type Model = (FsmBdd<BddManager>, Properties, Option<Aiger>);
+
+/// Loads a hardware model in the AIGER or BTOR2 format.
+pub fn load_model(manager: &BddManager, path: &str, args: &Args) -> Result<Model, String> {
+    if aiger::is_aiger(path) {
+        let aiger = Aiger::from_file(path)?;
+        let (fsmbdd, properties) = aiger.to_fsmbdd(manager, args.trans_method.into());
+        Ok((fsmbdd, properties, Some(aiger)))
+    } else {
+        let (fsmbdd, properties) = btor2::from_file(path, manager, args.trans_method.into())?;
+        Ok((fsmbdd, properties, None))
+    }
+}
+
+pub fn is_hardware_model(path: &str) -> bool {
+    aiger::is_aiger(path) || btor2::is_btor2(path)
+}
+
+/// Checks a hardware model, printing an AIGER witness when the model is an AIGER circuit.
+pub fn check(manager: BddManager, path: &str, args: Args) -> (Verdict, Duration) {
+    let (fsmbdd, properties, aiger) = match load_model(&manager, path, &args) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    let property = args.property;
+    let (res, time) = check_model(manager, fsmbdd, properties, args);
+    match aiger {
+        Some(aiger) => print!("{}", aiger.witness(property, &res)),
+        None => println!("res: {}", res),
+    }
+    (res, time)
+}
+
+fn check_model(
+    manager: BddManager,
+    fsmbdd: FsmBdd<BddManager>,
+    properties: Properties,
+    args: Args,
+) -> (Verdict, Duration) {
+    let (fsmbdd, _) = match apply_order_options(&args, fsmbdd, HashMap::new()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    let ba = match properties.automata(&manager, &fsmbdd.symbols, args.property) {
+        Ok(ba) => ba,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    match args.algorithm {
+        Algorithm::Partitioned => partitioned::check_automata(manager, fsmbdd, ba, args),
+        Algorithm::Traditional => traditional::check_automata(fsmbdd, ba, args),
+        algorithm => (
+            Verdict::Unknown(format!("{:?} does not support hardware models", algorithm)),
+            Duration::ZERO,
+        ),
+    }
+}
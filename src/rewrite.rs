@@ -0,0 +1,312 @@
+use smv::{Expr, Infix, Prefix};
+
+fn prefix(prefix: Prefix, expr: Expr) -> Expr {
+    Expr::PrefixExpr(prefix, Box::new(expr))
+}
+
+fn infix(infix: Infix, left: Expr, right: Expr) -> Expr {
+    Expr::InfixExpr(infix, Box::new(left), Box::new(right))
+}
+
+/// Number of operators and atoms of `expr`.
+pub fn size(expr: &Expr) -> usize {
+    match expr {
+        Expr::PrefixExpr(_, expr) => 1 + size(expr),
+        Expr::InfixExpr(_, left, right) => 1 + size(left) + size(right),
+        _ => 1,
+    }
+}
+
+fn is_temporal_or_boolean(infix: &Infix) -> bool {
+    matches!(
+        infix,
+        Infix::And
+            | Infix::Or
+            | Infix::Imply
+            | Infix::Iff
+            | Infix::LtlUntil
+            | Infix::LtlReleases
+            | Infix::LtlSince
+            | Infix::LtlTriggered
+    )
+}
+
+/// Negation normal form, where negations only apply to atoms.
+pub fn nnf(expr: &Expr) -> Expr {
+    push_negation(expr, false)
+}
+
+fn push_negation(expr: &Expr, negate: bool) -> Expr {
+    match expr {
+        Expr::LitExpr(value) => Expr::LitExpr(*value != negate),
+        Expr::PrefixExpr(Prefix::Not, expr) => push_negation(expr, !negate),
+        Expr::PrefixExpr(op, arg) => {
+            let dual = match op {
+                Prefix::LtlGlobally => Prefix::LtlFinally,
+                Prefix::LtlFinally => Prefix::LtlGlobally,
+                Prefix::LtlNext => Prefix::LtlNext,
+                Prefix::LtlYesterday => Prefix::LtlWeakYesterday,
+                Prefix::LtlWeakYesterday => Prefix::LtlYesterday,
+                Prefix::LtlOnce => Prefix::LtlHistorically,
+                Prefix::LtlHistorically => Prefix::LtlOnce,
+                _ => return negate_atom(expr, negate),
+            };
+            let op = if negate { dual } else { op.clone() };
+            prefix(op, push_negation(arg, negate))
+        }
+        Expr::InfixExpr(op, left, right) if is_temporal_or_boolean(op) => match op {
+            Infix::Imply => {
+                let left = push_negation(left, !negate);
+                let right = push_negation(right, negate);
+                infix(if negate { Infix::And } else { Infix::Or }, left, right)
+            }
+            Infix::Iff => {
+                let both = infix(Infix::And, *left.clone(), *right.clone());
+                let neither = infix(
+                    Infix::And,
+                    prefix(Prefix::Not, *left.clone()),
+                    prefix(Prefix::Not, *right.clone()),
+                );
+                push_negation(&infix(Infix::Or, both, neither), negate)
+            }
+            _ => {
+                let dual = match op {
+                    Infix::And => Infix::Or,
+                    Infix::Or => Infix::And,
+                    Infix::LtlUntil => Infix::LtlReleases,
+                    Infix::LtlReleases => Infix::LtlUntil,
+                    Infix::LtlSince => Infix::LtlTriggered,
+                    _ => Infix::LtlSince,
+                };
+                let op = if negate { dual } else { op.clone() };
+                infix(op, push_negation(left, negate), push_negation(right, negate))
+            }
+        },
+        _ => negate_atom(expr, negate),
+    }
+}
+
+fn negate_atom(expr: &Expr, negate: bool) -> Expr {
+    if negate {
+        prefix(Prefix::Not, expr.clone())
+    } else {
+        expr.clone()
+    }
+}
+
+/// Formulas whose truth is preserved by prepending a finite prefix to the path, such as
+/// `F a`. For these `F e = e` and `a U e = e`.
+fn is_eventual(expr: &Expr) -> bool {
+    match expr {
+        Expr::LitExpr(_) => true,
+        Expr::PrefixExpr(Prefix::LtlFinally, _) => true,
+        Expr::PrefixExpr(Prefix::LtlGlobally | Prefix::LtlNext, expr) => is_eventual(expr),
+        Expr::InfixExpr(Infix::And | Infix::Or, left, right) => {
+            is_eventual(left) && is_eventual(right)
+        }
+        Expr::InfixExpr(Infix::LtlUntil, _, right) => is_eventual(right),
+        _ => false,
+    }
+}
+
+/// Formulas whose truth is preserved by removing a finite prefix of the path, such as
+/// `G a`. For these `G u = u` and `a V u = u`.
+fn is_universal(expr: &Expr) -> bool {
+    match expr {
+        Expr::LitExpr(_) => true,
+        Expr::PrefixExpr(Prefix::LtlGlobally, _) => true,
+        Expr::PrefixExpr(Prefix::LtlFinally | Prefix::LtlNext, expr) => is_universal(expr),
+        Expr::InfixExpr(Infix::And | Infix::Or, left, right) => {
+            is_universal(left) && is_universal(right)
+        }
+        Expr::InfixExpr(Infix::LtlReleases, _, right) => is_universal(right),
+        _ => false,
+    }
+}
+
+fn flatten(op: &Infix, expr: Expr, operands: &mut Vec<Expr>) {
+    match expr {
+        Expr::InfixExpr(inner, left, right) if inner == *op => {
+            flatten(op, *left, operands);
+            flatten(op, *right, operands);
+        }
+        expr => operands.push(expr),
+    }
+}
+
+/// Simplifies a conjunction or disjunction: constants are propagated, duplicate operands
+/// removed, and the operands under `G` and `X` of a conjunction, or under `F` and `X` of a
+/// disjunction, are grouped, so that `G F a & G F b` becomes `G (F a & F b)`.
+fn simplify_junction(op: Infix, left: Expr, right: Expr) -> Expr {
+    let (unit, zero, grouped) = match op {
+        Infix::And => (true, false, Prefix::LtlGlobally),
+        _ => (false, true, Prefix::LtlFinally),
+    };
+    let mut operands = Vec::new();
+    flatten(&op, left, &mut operands);
+    flatten(&op, right, &mut operands);
+    let mut seen = Vec::new();
+    let mut plain = Vec::new();
+    let mut under_grouped = Vec::new();
+    let mut under_next = Vec::new();
+    for operand in operands {
+        match operand {
+            Expr::LitExpr(value) if value == unit => continue,
+            Expr::LitExpr(value) if value == zero => return Expr::LitExpr(zero),
+            _ => (),
+        }
+        let key = operand.to_string();
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        match operand {
+            Expr::PrefixExpr(prefix, arg) if prefix == grouped => under_grouped.push(*arg),
+            Expr::PrefixExpr(Prefix::LtlNext, arg) => under_next.push(*arg),
+            operand => plain.push(operand),
+        }
+    }
+    let join = |operands: Vec<Expr>| {
+        operands
+            .into_iter()
+            .reduce(|fold, operand| infix(op.clone(), fold, operand))
+    };
+    if under_grouped.len() > 1 {
+        let inner = join(under_grouped).unwrap();
+        plain.push(simplify(&prefix(grouped, inner)));
+    } else {
+        plain.extend(under_grouped.into_iter().map(|arg| prefix(grouped.clone(), arg)));
+    }
+    if under_next.len() > 1 {
+        let inner = join(under_next).unwrap();
+        plain.push(simplify(&prefix(Prefix::LtlNext, inner)));
+    } else {
+        plain.extend(under_next.into_iter().map(|arg| prefix(Prefix::LtlNext, arg)));
+    }
+    join(plain).unwrap_or(Expr::LitExpr(unit))
+}
+
+/// Simplifies a formula in negation normal form, bottom up.
+pub fn simplify(expr: &Expr) -> Expr {
+    match expr {
+        Expr::PrefixExpr(op, arg) => {
+            let arg = simplify(arg);
+            match (op, arg) {
+                (Prefix::Not, Expr::LitExpr(value)) => Expr::LitExpr(!value),
+                (Prefix::LtlGlobally | Prefix::LtlFinally | Prefix::LtlNext, Expr::LitExpr(v)) => {
+                    Expr::LitExpr(v)
+                }
+                (Prefix::LtlFinally, arg) if is_eventual(&arg) => arg,
+                (Prefix::LtlGlobally, arg) if is_universal(&arg) => arg,
+                (Prefix::LtlNext, arg) if is_eventual(&arg) && is_universal(&arg) => arg,
+                (Prefix::LtlFinally, Expr::InfixExpr(Infix::LtlUntil, _, right)) => {
+                    simplify(&prefix(Prefix::LtlFinally, *right))
+                }
+                (Prefix::LtlGlobally, Expr::InfixExpr(Infix::LtlReleases, _, right)) => {
+                    simplify(&prefix(Prefix::LtlGlobally, *right))
+                }
+                (op, arg) => prefix(op.clone(), arg),
+            }
+        }
+        Expr::InfixExpr(op, left, right) if is_temporal_or_boolean(op) => {
+            let left = simplify(left);
+            let right = simplify(right);
+            match op {
+                Infix::And | Infix::Or => simplify_junction(op.clone(), left, right),
+                Infix::LtlUntil => match (left, right) {
+                    (_, Expr::LitExpr(value)) => Expr::LitExpr(value),
+                    (Expr::LitExpr(true), right) => simplify(&prefix(Prefix::LtlFinally, right)),
+                    (Expr::LitExpr(false), right) => right,
+                    (_, right) if is_eventual(&right) => right,
+                    (left, right) => infix(Infix::LtlUntil, left, right),
+                },
+                Infix::LtlReleases => match (left, right) {
+                    (_, Expr::LitExpr(value)) => Expr::LitExpr(value),
+                    (Expr::LitExpr(false), right) => simplify(&prefix(Prefix::LtlGlobally, right)),
+                    (Expr::LitExpr(true), right) => right,
+                    (_, right) if is_universal(&right) => right,
+                    (left, right) => infix(Infix::LtlReleases, left, right),
+                },
+                op => infix(op.clone(), left, right),
+            }
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Rewrites an LTL formula before its translation to an automaton, reporting its size
+/// before and after.
+pub fn rewrite_ltl(ltl: &Expr) -> Expr {
+    let res = simplify(&nnf(ltl));
+    eprintln!("ltl rewrite: size {} -> {}", size(ltl), size(&res));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(name: &str) -> Expr {
+        Expr::Ident(name.to_string())
+    }
+
+    fn not(expr: Expr) -> Expr {
+        prefix(Prefix::Not, expr)
+    }
+
+    #[test]
+    fn nnf_duals() {
+        let (a, b) = (atom("a"), atom("b"));
+        let duals = [
+            (Prefix::LtlGlobally, Prefix::LtlFinally),
+            (Prefix::LtlNext, Prefix::LtlNext),
+            (Prefix::LtlYesterday, Prefix::LtlWeakYesterday),
+            (Prefix::LtlWeakYesterday, Prefix::LtlYesterday),
+            (Prefix::LtlOnce, Prefix::LtlHistorically),
+            (Prefix::LtlHistorically, Prefix::LtlOnce),
+        ];
+        for (op, dual) in duals {
+            let expr = not(prefix(op, a.clone()));
+            assert_eq!(nnf(&expr), prefix(dual, not(a.clone())));
+        }
+        let duals = [
+            (Infix::LtlUntil, Infix::LtlReleases),
+            (Infix::LtlSince, Infix::LtlTriggered),
+            (Infix::LtlTriggered, Infix::LtlSince),
+        ];
+        for (op, dual) in duals {
+            let expr = not(infix(op, a.clone(), b.clone()));
+            assert_eq!(nnf(&expr), infix(dual, not(a.clone()), not(b.clone())));
+        }
+    }
+
+    #[test]
+    fn group_globally() {
+        let gf = |name| prefix(Prefix::LtlGlobally, prefix(Prefix::LtlFinally, atom(name)));
+        let expr = infix(Infix::And, gf("a"), gf("b"));
+        let fa = prefix(Prefix::LtlFinally, atom("a"));
+        let fb = prefix(Prefix::LtlFinally, atom("b"));
+        let expected = prefix(Prefix::LtlGlobally, infix(Infix::And, fa, fb));
+        assert_eq!(simplify(&expr), expected);
+    }
+
+    #[test]
+    fn eventual_until() {
+        let fb = prefix(Prefix::LtlFinally, atom("b"));
+        let expr = infix(Infix::LtlUntil, atom("a"), fb.clone());
+        assert_eq!(simplify(&expr), fb);
+    }
+
+    #[test]
+    fn constants() {
+        let a = atom("a");
+        let and = infix(Infix::And, a.clone(), Expr::LitExpr(true));
+        assert_eq!(simplify(&and), a);
+        let or = infix(Infix::Or, a.clone(), Expr::LitExpr(true));
+        assert_eq!(simplify(&or), Expr::LitExpr(true));
+        let globally = prefix(Prefix::LtlGlobally, Expr::LitExpr(false));
+        assert_eq!(simplify(&nnf(&not(globally))), Expr::LitExpr(true));
+        let until = infix(Infix::LtlUntil, Expr::LitExpr(true), a.clone());
+        assert_eq!(simplify(&until), prefix(Prefix::LtlFinally, a));
+    }
+}
@@ -0,0 +1,518 @@
+use std::ops::Not;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, positive: bool) -> Self {
+        Self((var as u32) << 1 | !positive as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+fn lit_value(assigns: &[Option<bool>], lit: Lit) -> Option<bool> {
+    assigns[lit.var()].map(|value| value == lit.is_positive())
+}
+
+/// Binary max heap of variables ordered by activity, holding at least the unassigned
+/// variables.
+#[derive(Default)]
+struct VarHeap {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    fn contains(&self, var: usize) -> bool {
+        self.position[var].is_some()
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.position[self.heap[i]] = Some(i);
+        self.position[self.heap[j]] = Some(j);
+    }
+
+    fn sift_up(&mut self, mut i: usize, activity: &[f64]) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if activity[self.heap[parent]] >= activity[self.heap[i]] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize, activity: &[f64]) {
+        loop {
+            let mut largest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len()
+                    && activity[self.heap[child]] > activity[self.heap[largest]]
+                {
+                    largest = child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    fn push(&mut self, var: usize, activity: &[f64]) {
+        if self.position.len() <= var {
+            self.position.resize(var + 1, None);
+        }
+        if self.contains(var) {
+            return;
+        }
+        self.heap.push(var);
+        self.position[var] = Some(self.heap.len() - 1);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let last = self.heap.len().checked_sub(1)?;
+        self.swap(0, last);
+        let var = self.heap.pop().unwrap();
+        self.position[var] = None;
+        self.sift_down(0, activity);
+        Some(var)
+    }
+
+    /// Restores the heap order after the activity of `var` increased.
+    fn increased(&mut self, var: usize, activity: &[f64]) {
+        if let Some(i) = self.position[var] {
+            self.sift_up(i, activity);
+        }
+    }
+}
+
+/// A small CDCL solver with two watched literals, first UIP learning, activity based
+/// branching and geometric restarts. It is incremental: clauses can be added between
+/// solves, which may assume literals.
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    phase: Vec<bool>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    activity: Vec<f64>,
+    order: VarHeap,
+    var_inc: f64,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    unsat: bool,
+    model: Vec<bool>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            var_inc: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn num_var(&self) -> usize {
+        self.assigns.len()
+    }
+
+    pub fn new_var(&mut self) -> Lit {
+        let var = self.num_var();
+        self.assigns.push(None);
+        self.phase.push(false);
+        self.level.push(0);
+        self.reason.push(None);
+        self.activity.push(0.0);
+        self.order.push(var, &self.activity);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        Lit::new(var, true)
+    }
+
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        self.cancel_until(0);
+        let mut clause = lits.to_vec();
+        clause.sort_by_key(|lit| lit.0);
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0] == !w[1]) {
+            return;
+        }
+        if clause
+            .iter()
+            .any(|lit| lit_value(&self.assigns, *lit) == Some(true))
+        {
+            return;
+        }
+        clause.retain(|lit| lit_value(&self.assigns, *lit).is_none());
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.enqueue(clause[0], None);
+                if self.propagate().is_some() {
+                    self.unsat = true;
+                }
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let id = self.clauses.len();
+        self.watches[clause[0].index()].push(id);
+        self.watches[clause[1].index()].push(id);
+        self.clauses.push(clause);
+        id
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(lit.is_positive());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let id = watchers[i];
+                let clause = &mut self.clauses[id];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if lit_value(&self.assigns, first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                let new_watch = (2..clause.len())
+                    .find(|k| lit_value(&self.assigns, clause[*k]) != Some(false));
+                if let Some(k) = new_watch {
+                    clause.swap(1, k);
+                    let watch = clause[1];
+                    self.watches[watch.index()].push(id);
+                    watchers.swap_remove(i);
+                    continue;
+                }
+                if lit_value(&self.assigns, first) == Some(false) {
+                    conflict = Some(id);
+                    break;
+                }
+                self.enqueue(first, Some(id));
+                i += 1;
+            }
+            self.watches[false_lit.index()] = watchers;
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.var_inc *= 1e-100;
+        }
+        self.order.increased(var, &self.activity);
+    }
+
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut seen = vec![false; self.num_var()];
+        let mut learnt = vec![Lit(0)];
+        let mut counter = 0;
+        let mut index = self.trail.len();
+        let mut p: Option<Lit> = None;
+        loop {
+            let start = if p.is_none() { 0 } else { 1 };
+            for j in start..self.clauses[conflict].len() {
+                let q = self.clauses[conflict][j];
+                let var = q.var();
+                if !seen[var] && self.level[var] > 0 {
+                    seen[var] = true;
+                    self.bump(var);
+                    if self.level[var] >= self.decision_level() {
+                        counter += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            seen[lit.var()] = false;
+            p = Some(lit);
+            counter -= 1;
+            if counter == 0 {
+                break;
+            }
+            conflict = self.reason[lit.var()].unwrap();
+        }
+        learnt[0] = !p.unwrap();
+        let mut backtrack = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len())
+                .max_by_key(|i| self.level[learnt[*i].var()])
+                .unwrap();
+            learnt.swap(1, max);
+            backtrack = self.level[learnt[1].var()];
+        }
+        (learnt, backtrack)
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let lim = self.trail_lim[level];
+        for lit in self.trail.drain(lim..) {
+            self.assigns[lit.var()] = None;
+            self.phase[lit.var()] = lit.is_positive();
+            self.reason[lit.var()] = None;
+            self.order.push(lit.var(), &self.activity);
+        }
+        self.trail_lim.truncate(level);
+        self.qhead = self.trail.len();
+    }
+
+    fn pick_branch(&mut self) -> Option<Lit> {
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.assigns[var].is_none() {
+                return Some(Lit::new(var, self.phase[var]));
+            }
+        }
+        None
+    }
+
+    pub fn solve(&mut self) -> bool {
+        self.solve_with(&[])
+    }
+
+    /// Solves under the `assumptions`, which are decided first. Clauses learnt under them
+    /// stay valid for later solves.
+    pub fn solve_with(&mut self, assumptions: &[Lit]) -> bool {
+        if self.unsat {
+            return false;
+        }
+        self.cancel_until(0);
+        let mut conflicts = 0;
+        let mut restart_limit = 100;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.decision_level() == 0 {
+                    self.unsat = true;
+                    return false;
+                }
+                let (learnt, backtrack) = self.analyze(conflict);
+                self.cancel_until(backtrack);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let lit = learnt[0];
+                    let id = self.attach(learnt);
+                    self.enqueue(lit, Some(id));
+                }
+                self.var_inc /= 0.95;
+                conflicts += 1;
+            } else {
+                if conflicts >= restart_limit {
+                    conflicts = 0;
+                    restart_limit += restart_limit / 2;
+                    self.cancel_until(0);
+                    continue;
+                }
+                if let Some(assumption) = assumptions.get(self.decision_level()) {
+                    let value = lit_value(&self.assigns, *assumption);
+                    if value == Some(false) {
+                        self.cancel_until(0);
+                        return false;
+                    }
+                    self.trail_lim.push(self.trail.len());
+                    if value.is_none() {
+                        self.enqueue(*assumption, None);
+                    }
+                    continue;
+                }
+                match self.pick_branch() {
+                    Some(lit) => {
+                        self.trail_lim.push(self.trail.len());
+                        self.enqueue(lit, None);
+                    }
+                    None => {
+                        self.model = self.assigns.iter().map(|a| a.unwrap_or(false)).collect();
+                        self.cancel_until(0);
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Value of `lit` in the model of the last satisfiable solve.
+    pub fn model_value(&self, lit: Lit) -> bool {
+        self.model.get(lit.var()).copied().unwrap_or(false) == lit.is_positive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solver_with(num_var: usize, clauses: &[Vec<i32>]) -> (Solver, Vec<Lit>) {
+        let mut solver = Solver::new();
+        let vars: Vec<Lit> = (0..num_var).map(|_| solver.new_var()).collect();
+        for clause in clauses.iter() {
+            solver.add_clause(&lits(&vars, clause));
+        }
+        (solver, vars)
+    }
+
+    /// DIMACS style literals, `-2` is the negation of the second variable.
+    fn lits(vars: &[Lit], clause: &[i32]) -> Vec<Lit> {
+        clause
+            .iter()
+            .map(|lit| {
+                let var = vars[lit.unsigned_abs() as usize - 1];
+                if *lit > 0 {
+                    var
+                } else {
+                    !var
+                }
+            })
+            .collect()
+    }
+
+    fn satisfies(solver: &Solver, vars: &[Lit], clauses: &[Vec<i32>]) -> bool {
+        clauses.iter().all(|clause| {
+            lits(vars, clause)
+                .into_iter()
+                .any(|lit| solver.model_value(lit))
+        })
+    }
+
+    #[test]
+    fn sat() {
+        let clauses = vec![vec![1, 2], vec![-1, 2], vec![-2, 3], vec![-3, -1, 4]];
+        let (mut solver, vars) = solver_with(4, &clauses);
+        assert!(solver.solve());
+        assert!(satisfies(&solver, &vars, &clauses));
+    }
+
+    #[test]
+    fn pigeonhole_unsat() {
+        // three pigeons in two holes, pigeon p in hole h is variable 2 * p + h + 1
+        let mut clauses = Vec::new();
+        for p in 0..3 {
+            clauses.push(vec![2 * p + 1, 2 * p + 2]);
+        }
+        for h in 0..2 {
+            for p in 0..3 {
+                for q in p + 1..3 {
+                    clauses.push(vec![-(2 * p + h + 1), -(2 * q + h + 1)]);
+                }
+            }
+        }
+        let (mut solver, _) = solver_with(6, &clauses);
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn assumptions() {
+        let clauses = vec![vec![1, 2], vec![-1, 3]];
+        let (mut solver, vars) = solver_with(3, &clauses);
+        assert!(!solver.solve_with(&[!vars[1], !vars[2]]));
+        assert!(solver.solve_with(&[!vars[1]]));
+        assert!(solver.model_value(vars[0]) && solver.model_value(vars[2]));
+        solver.add_clause(&[!vars[0]]);
+        assert!(solver.solve());
+        assert!(solver.model_value(vars[1]));
+        solver.add_clause(&[!vars[1]]);
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn random_against_brute_force() {
+        let mut seed: u64 = 1;
+        let mut random = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..200 {
+            let num_var = 8;
+            let clauses: Vec<Vec<i32>> = (0..34)
+                .map(|_| {
+                    (0..3)
+                        .map(|_| {
+                            let var = random(num_var) as i32 + 1;
+                            if random(2) == 0 {
+                                var
+                            } else {
+                                -var
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            let brute_force = (0..1u32 << num_var).any(|assignment| {
+                clauses.iter().all(|clause| {
+                    clause.iter().any(|lit| {
+                        let value = assignment >> (lit.unsigned_abs() - 1) & 1 == 1;
+                        value == (*lit > 0)
+                    })
+                })
+            });
+            let (mut solver, vars) = solver_with(num_var as usize, &clauses);
+            assert_eq!(solver.solve(), brute_force);
+            if brute_force {
+                assert!(satisfies(&solver, &vars, &clauses));
+            }
+        }
+    }
+}
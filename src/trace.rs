@@ -0,0 +1,31 @@
+use crate::formula::{collapse_assignment, Domains};
+use std::fmt::Display;
+
+/// A concrete lasso or finite path over the model symbols.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub states: Vec<Vec<(String, bool)>>,
+    pub loop_start: Option<usize>,
+    /// Literals of the enumerations among the states' vectors.
+    pub domains: Domains,
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut last: Option<Vec<(String, String)>> = None;
+        for (i, state) in self.states.iter().enumerate() {
+            if self.loop_start == Some(i) {
+                writeln!(f, "-- Loop starts here")?;
+            }
+            writeln!(f, "-> State: 1.{} <-", i + 1)?;
+            let state = collapse_assignment(state, &self.domains);
+            for (j, (name, value)) in state.iter().enumerate() {
+                if last.as_ref().map_or(true, |last| last[j].1 != *value) {
+                    writeln!(f, "  {} = {}", name, value)?;
+                }
+            }
+            last = Some(state);
+        }
+        Ok(())
+    }
+}
@@ -1,26 +1,52 @@
-use crate::{automata::BuchiAutomata, command::Args, ltl::ltl_to_automata_preprocess, BddManager};
+use crate::{
+    automata::BuchiAutomata,
+    command::{Args, ParallelMode},
+    export::dump_automaton,
+    ltl::{ltl_automata, ltl_spec},
+    order::apply_order_options,
+    verdict::Verdict,
+    BddManager,
+};
+use fsmbdd::FsmBdd;
 use smv::{bdd::SmvBdd, Smv};
 use std::time::{Duration, Instant};
 use sylvan::lace_run;
 
-pub fn check(manager: BddManager, smv: Smv, args: Args) -> (bool, Duration) {
+pub fn check(manager: BddManager, smv: Smv, args: Args) -> (Verdict, Duration) {
     let smvbdd = SmvBdd::new(&manager, &smv);
     let fsmbdd = smvbdd.to_fsmbdd(args.trans_method.into());
-    let ltl = ltl_to_automata_preprocess(&smv, !smv.ltlspecs[0].clone());
-    let ltl_fsmbdd =
-        BuchiAutomata::from_ltl(ltl, &manager, &smvbdd.symbols, &smvbdd.defines).to_fsmbdd();
-    let product = fsmbdd.product(&ltl_fsmbdd);
-    println!("traditional smc begin");
+    let (mut fsmbdd, defines) = match apply_order_options(&args, fsmbdd, smvbdd.defines.clone()) {
+        Ok(model) => model,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    let ba = ltl_spec(&smv, args.property.index)
+        .and_then(|ltl| ltl_automata(&smv, &!ltl, &mut fsmbdd, &defines, &args));
+    let ba = match ba {
+        Ok(ba) => ba,
+        Err(error) => return (Verdict::Unknown(error), Duration::ZERO),
+    };
+    check_automata(fsmbdd, ba, args)
+}
+
+pub fn check_automata(
+    fsmbdd: FsmBdd<BddManager>,
+    ba: BuchiAutomata,
+    args: Args,
+) -> (Verdict, Duration) {
+    dump_automaton(&args, &ba);
+    let product = fsmbdd.product(&ba.to_fsmbdd());
+    eprintln!("traditional smc begin");
     let start = Instant::now();
-    let forward = if args.close_lace_optimize {
+    let forward = if args.parallel_mode != ParallelMode::Lace {
         product.reachable_from_init()
     } else {
         lace_run(|_| product.reachable_from_init())
     };
-    let fair_cycle = if args.close_lace_optimize {
+    let fair_cycle = if args.parallel_mode != ParallelMode::Lace {
         product.fair_cycle_with_constrain(&forward)
     } else {
         lace_run(|_| product.fair_cycle_with_constrain(&forward))
     };
-    ((fair_cycle & forward).is_constant(false), start.elapsed())
+    let holds = (fair_cycle & forward).is_constant(false);
+    (Verdict::from_holds(holds), start.elapsed())
 }
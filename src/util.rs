@@ -1,4 +1,6 @@
-use smv::{Expr, Prefix};
+use crate::{Bdd, BddManager};
+use smv::{Expr, Infix, Prefix};
+use std::collections::HashMap;
 
 fn trans_expr_to_ltl_rec(expr: &Expr) -> Expr {
     match expr {
@@ -10,7 +12,20 @@ fn trans_expr_to_ltl_rec(expr: &Expr) -> Expr {
         },
         Expr::Ident(_) => expr.clone(),
         Expr::LitExpr(_) => expr.clone(),
-        Expr::CaseExpr(_) => todo!(),
+        Expr::CaseExpr(case) => {
+            case.branch
+                .iter()
+                .rev()
+                .fold(Expr::LitExpr(false), |rest, (cond, value)| {
+                    let cond = trans_expr_to_ltl_rec(cond);
+                    let value = trans_expr_to_ltl_rec(value);
+                    Expr::InfixExpr(
+                        Infix::Or,
+                        Box::new(cond.clone() & value),
+                        Box::new(!cond & rest),
+                    )
+                })
+        }
         Expr::InfixExpr(infix, left, right) => Expr::InfixExpr(
             infix.clone(),
             Box::new(trans_expr_to_ltl_rec(left)),
@@ -22,3 +37,24 @@ fn trans_expr_to_ltl_rec(expr: &Expr) -> Expr {
 pub fn trans_expr_to_ltl(expr: &Expr) -> Expr {
     trans_expr_to_ltl_rec(expr)
 }
+
+/// Picks a single state of `states`, assigning every symbol.
+pub fn pick_state(symbols: &HashMap<String, usize>, manager: &BddManager, states: &Bdd) -> Bdd {
+    assert!(!states.is_constant(false));
+    let mut ids: Vec<usize> = symbols.values().copied().collect();
+    ids.sort();
+    let mut states = states.clone();
+    let mut cube = manager.constant(true);
+    for id in ids {
+        let var = manager.ith_var(id);
+        let positive = &states & &var;
+        if positive.is_constant(false) {
+            states &= !&var;
+            cube &= !var;
+        } else {
+            states = positive;
+            cube &= var;
+        }
+    }
+    cube
+}
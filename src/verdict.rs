@@ -0,0 +1,66 @@
+use crate::{budget::LimitExceeded, trace::Trace};
+use std::fmt::Display;
+
+/// Result of checking a property.
+#[derive(Debug, Clone)]
+pub enum Verdict {
+    Holds,
+    /// The property is violated, with a counterexample if the algorithm produces one.
+    Violated(Option<Trace>),
+    /// The check gave up, with the reason.
+    Unknown(String),
+}
+
+impl Verdict {
+    pub fn from_holds(holds: bool) -> Self {
+        if holds {
+            Verdict::Holds
+        } else {
+            Verdict::Violated(None)
+        }
+    }
+
+    /// Process exit code, following the SAT solver convention of the HWMCC: 20 when the
+    /// property holds, 10 when it is violated and 30 when unknown, so that an unknown
+    /// result is told apart from both a crash and a successful run.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Verdict::Holds => 20,
+            Verdict::Violated(_) => 10,
+            Verdict::Unknown(_) => 30,
+        }
+    }
+
+    /// NuSMV style result of `spec`, followed by the counterexample if there is one.
+    pub fn nusmv(&self, spec: &str) -> String {
+        match self {
+            Verdict::Holds => format!("-- specification {} is true\n", spec),
+            Verdict::Violated(None) => format!("-- specification {} is false\n", spec),
+            Verdict::Violated(Some(trace)) => format!(
+                "-- specification {} is false\n\
+                 -- as demonstrated by the following execution sequence\n\
+                 Trace Type: Counterexample\n{}",
+                spec, trace
+            ),
+            Verdict::Unknown(reason) => {
+                format!("-- specification {} is unknown: {}\n", spec, reason)
+            }
+        }
+    }
+}
+
+impl From<LimitExceeded> for Verdict {
+    fn from(limit: LimitExceeded) -> Self {
+        Verdict::Unknown(limit.to_string())
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Holds => write!(f, "holds"),
+            Verdict::Violated(_) => write!(f, "violated"),
+            Verdict::Unknown(reason) => write!(f, "unknown ({})", reason),
+        }
+    }
+}